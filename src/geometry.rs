@@ -0,0 +1,6 @@
+pub mod mesh_data;
+pub mod optimize;
+pub mod primitives;
+pub mod simplify;
+pub(crate) mod vector;
+pub mod vertex;
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use crate::geometry::vector::{add, cross, dot, length, normalize, scale, sub};
+use crate::geometry::vertex::{FromVertex, Vertex};
+use crate::renderer::context::Context;
+use crate::renderer::mesh::Mesh;
+
+/// An axis aligned box enclosing a mesh.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3]
+}
+
+impl Bounds {
+    pub fn get_center(&self) -> [f32; 3] {
+        scale(add(self.min, self.max), 0.5)
+    }
+
+    pub fn get_size(&self) -> [f32; 3] {
+        sub(self.max, self.min)
+    }
+
+    /// The radius of the sphere around the center that encloses the whole box.
+    pub fn get_radius(&self) -> f32 {
+        length(self.get_size()) / 2.0
+    }
+}
+
+/// Geometry kept on the CPU, as an indexed triangle list.
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>
+}
+
+fn attributes(vertex: &Vertex) -> [f32; 16] {
+    let Vertex { position: p, normal: n, tangent: t, uv, color: c } = *vertex;
+    [p[0], p[1], p[2], n[0], n[1], n[2], t[0], t[1], t[2], t[3], uv[0], uv[1], c[0], c[1], c[2], c[3]]
+}
+
+fn position_key(position: [f32; 3]) -> [u32; 3] {
+    [position[0].to_bits(), position[1].to_bits(), position[2].to_bits()]
+}
+
+/// Any unit vector perpendicular to `normal`.
+fn perpendicular(normal: [f32; 3]) -> [f32; 3] {
+    let axis = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    normalize(cross(axis, normal))
+}
+
+impl MeshData {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> MeshData {
+        MeshData {
+            vertices,
+            indices
+        }
+    }
+
+    pub fn to_vertices<V: FromVertex>(&self) -> Vec<V> {
+        self.vertices.iter().map(V::from_vertex).collect()
+    }
+
+    pub fn to_mesh<V: FromVertex + Copy + Clone + 'static>(&self, context: &Context) -> Mesh<V, u32> {
+        Mesh::new(self.to_vertices(), self.indices.clone(), context)
+    }
+
+    /// Returns `None` for a mesh without vertices.
+    pub fn compute_bounds(&self) -> Option<Bounds> {
+        let first = self.vertices.first()?.position;
+        Some(self.vertices.iter().fold(Bounds { min: first, max: first }, |bounds, vertex| {
+            let p = vertex.position;
+            Bounds {
+                min: [bounds.min[0].min(p[0]), bounds.min[1].min(p[1]), bounds.min[2].min(p[2])],
+                max: [bounds.max[0].max(p[0]), bounds.max[1].max(p[1]), bounds.max[2].max(p[2])]
+            }
+        }))
+    }
+
+    /// Reverses the winding of every triangle. Normals are left as they are.
+    pub fn flip_winding(&mut self) {
+        for triangle in self.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    /// Gives every triangle its own vertices carrying the triangle's normal.
+    pub fn generate_flat_normals(&mut self) {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [
+                self.vertices[triangle[0] as usize],
+                self.vertices[triangle[1] as usize],
+                self.vertices[triangle[2] as usize]
+            ];
+            let normal = normalize(cross(
+                sub(corners[1].position, corners[0].position),
+                sub(corners[2].position, corners[0].position)
+            ));
+            for &corner in &corners {
+                vertices.push(Vertex {
+                    normal,
+                    ..corner
+                });
+            }
+        }
+        self.indices = (0..vertices.len() as u32).collect();
+        self.vertices = vertices;
+    }
+
+    /// Averages the normals of the triangles around each position, weighted by their area.
+    /// Vertices split along UV seams share the same normal.
+    pub fn generate_smooth_normals(&mut self) {
+        let mut normals: HashMap<[u32; 3], [f32; 3]> = HashMap::new();
+        for triangle in self.indices.chunks_exact(3) {
+            let a = self.vertices[triangle[0] as usize].position;
+            let b = self.vertices[triangle[1] as usize].position;
+            let c = self.vertices[triangle[2] as usize].position;
+            let normal = cross(sub(b, a), sub(c, a));
+            for &position in &[a, b, c] {
+                let sum = normals.entry(position_key(position)).or_insert([0.0; 3]);
+                *sum = add(*sum, normal);
+            }
+        }
+        for vertex in &mut self.vertices {
+            if let Some(&normal) = normals.get(&position_key(vertex.position)) {
+                vertex.normal = normalize(normal);
+            }
+        }
+    }
+
+    /// Generates tangents from the normals and texture coordinates the way MikkTSpace does,
+    /// so normal maps baked by other tools line up. Normals have to be present already.
+    ///
+    /// Triangles around a vertex are grouped by the handedness of their UV mapping and each
+    /// group gets its own tangent, the sum of the triangles' tangents projected on the normal's
+    /// plane and weighted by the corner angle. Vertices shared across a mirrored UV seam are
+    /// split. Unlike the reference implementation, triangles are grouped by handedness alone,
+    /// not by the fans they're connected through.
+    ///
+    /// Texture coordinates are expected with a top-left origin, so `w * cross(normal, tangent)`
+    /// points towards decreasing v, which matches glTF and OpenGL style normal maps.
+    pub fn generate_tangents(&mut self) {
+        // Triangles with a zero UV area have no orientation and join any group.
+        let faces: Vec<Option<([f32; 3], bool)>> = self.indices.chunks_exact(3).map(|triangle| {
+            let corners = [
+                self.vertices[triangle[0] as usize],
+                self.vertices[triangle[1] as usize],
+                self.vertices[triangle[2] as usize]
+            ];
+            let e1 = sub(corners[1].position, corners[0].position);
+            let e2 = sub(corners[2].position, corners[0].position);
+            // MikkTSpace works with v pointing up.
+            let (du1, dv1) = (corners[1].uv[0] - corners[0].uv[0], corners[0].uv[1] - corners[1].uv[1]);
+            let (du2, dv2) = (corners[2].uv[0] - corners[0].uv[0], corners[0].uv[1] - corners[2].uv[1]);
+            let area = du1 * dv2 - dv1 * du2;
+            if area == 0.0 {
+                return None;
+            }
+            let tangent = normalize(sub(scale(e1, dv2), scale(e2, dv1)));
+            Some((scale(tangent, area.signum()), area > 0.0))
+        }).collect();
+
+        // Vertices with the same position, normal and uv are treated as one, even when the
+        // mesh doesn't share them.
+        let key = |vertex: &Vertex| {
+            let Vertex { position: p, normal: n, uv, .. } = *vertex;
+            [p[0], p[1], p[2], n[0], n[1], n[2], uv[0], uv[1]].map(f32::to_bits)
+        };
+        let mut groups: HashMap<([u32; 8], bool), usize> = HashMap::new();
+        let mut first_group: HashMap<[u32; 8], usize> = HashMap::new();
+        let mut sums: Vec<([f32; 3], bool)> = Vec::new();
+        let mut corner_groups = vec![usize::MAX; self.indices.len()];
+
+        for (i, (face, triangle)) in faces.iter().zip(self.indices.chunks_exact(3)).enumerate() {
+            let (tangent, preserving) = match *face {
+                Some(face) => face,
+                None => continue
+            };
+            for corner in 0..3 {
+                let vertex = &self.vertices[triangle[corner] as usize];
+                let group = *groups.entry((key(vertex), preserving)).or_insert_with(|| {
+                    sums.push(([0.0; 3], preserving));
+                    sums.len() - 1
+                });
+                first_group.entry(key(vertex)).or_insert(group);
+                corner_groups[i * 3 + corner] = group;
+
+                let normal = vertex.normal;
+                let project = |v: [f32; 3]| normalize(sub(v, scale(normal, dot(normal, v))));
+                let to_next = project(sub(self.vertices[triangle[(corner + 1) % 3] as usize].position, vertex.position));
+                let to_previous = project(sub(self.vertices[triangle[(corner + 2) % 3] as usize].position, vertex.position));
+                let angle = dot(to_next, to_previous).clamp(-1.0, 1.0).acos();
+                sums[group].0 = add(sums[group].0, scale(project(tangent), angle));
+            }
+        }
+        for (i, &index) in self.indices.iter().enumerate() {
+            if corner_groups[i] == usize::MAX {
+                let vertex_key = key(&self.vertices[index as usize]);
+                corner_groups[i] = *first_group.entry(vertex_key).or_insert_with(|| {
+                    sums.push(([0.0; 3], true));
+                    sums.len() - 1
+                });
+            }
+        }
+
+        // The first group seen at a vertex keeps it, the others get copies.
+        let mut vertex_groups: Vec<Option<usize>> = vec![None; self.vertices.len()];
+        let mut copies: HashMap<(u32, usize), u32> = HashMap::new();
+        for (i, index) in self.indices.iter_mut().enumerate() {
+            let group = corner_groups[i];
+            let (sum, preserving) = sums[group];
+            let mut vertex = self.vertices[*index as usize];
+            let mut tangent = normalize(sub(sum, scale(vertex.normal, dot(vertex.normal, sum))));
+            if length(tangent) < 0.5 {
+                tangent = perpendicular(vertex.normal);
+            }
+            vertex.tangent = [tangent[0], tangent[1], tangent[2], if preserving { 1.0 } else { -1.0 }];
+
+            match vertex_groups[*index as usize] {
+                None => {
+                    vertex_groups[*index as usize] = Some(group);
+                    self.vertices[*index as usize] = vertex;
+                },
+                Some(first) if first == group => {},
+                Some(_) => {
+                    let vertices = &mut self.vertices;
+                    *index = *copies.entry((*index, group)).or_insert_with(|| {
+                        vertices.push(vertex);
+                        vertices.len() as u32 - 1
+                    });
+                }
+            }
+        }
+        for (vertex, group) in self.vertices.iter_mut().zip(vertex_groups) {
+            if group.is_none() {
+                let tangent = perpendicular(vertex.normal);
+                vertex.tangent = [tangent[0], tangent[1], tangent[2], 1.0];
+            }
+        }
+    }
+
+    /// Merges vertices whose attributes all differ by at most `epsilon`, then drops the
+    /// triangles that collapsed.
+    pub fn weld(&mut self, epsilon: f32) {
+        let cell_size = epsilon.max(1e-6);
+        let cell = |position: [f32; 3]| {
+            [
+                (position[0] / cell_size).floor() as i64,
+                (position[1] / cell_size).floor() as i64,
+                (position[2] / cell_size).floor() as i64
+            ]
+        };
+
+        let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut welded: Vec<Vertex> = Vec::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        for vertex in &self.vertices {
+            let key = cell(vertex.position);
+            let values = attributes(vertex);
+            let mut found = None;
+            'search: for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let candidates = match grid.get(&[key[0] + x, key[1] + y, key[2] + z]) {
+                            Some(candidates) => candidates,
+                            None => continue
+                        };
+                        for &candidate in candidates {
+                            let other = attributes(&welded[candidate as usize]);
+                            if values.iter().zip(other.iter()).all(|(a, b)| (a - b).abs() <= epsilon) {
+                                found = Some(candidate);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            remap.push(match found {
+                Some(index) => index,
+                None => {
+                    let index = welded.len() as u32;
+                    welded.push(*vertex);
+                    grid.entry(key).or_default().push(index);
+                    index
+                }
+            });
+        }
+
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for triangle in self.indices.chunks_exact(3) {
+            let a = remap[triangle[0] as usize];
+            let b = remap[triangle[1] as usize];
+            let c = remap[triangle[2] as usize];
+            if a != b && b != c && c != a {
+                indices.extend_from_slice(&[a, b, c]);
+            }
+        }
+        self.vertices = welded;
+        self.indices = indices;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad(uvs: [[f32; 2]; 4]) -> MeshData {
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        let vertices = positions.iter().zip(&uvs).map(|(&position, &uv)| Vertex {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            uv,
+            ..Vertex::default()
+        }).collect();
+        MeshData::new(vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        assert!(a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn tangents_follow_u() {
+        let mut mesh = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+        mesh.generate_tangents();
+        for vertex in &mesh.vertices {
+            assert_close(vertex.tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_the_handedness() {
+        let mut mesh = quad([[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]);
+        mesh.generate_tangents();
+        for vertex in &mesh.vertices {
+            assert_close(vertex.tangent, [-1.0, 0.0, 0.0, -1.0]);
+        }
+
+        let mut mesh = quad([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+        mesh.generate_tangents();
+        for vertex in &mesh.vertices {
+            assert_close(vertex.tangent, [1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn weld_merges_duplicates_and_drops_collapsed_triangles() {
+        let mut mesh = quad([[0.0, 0.0]; 4]);
+        let mut duplicate = mesh.vertices[2];
+        duplicate.position[0] += 1e-4;
+        mesh.vertices.push(duplicate);
+        mesh.indices.extend_from_slice(&[1, 4, 3, 2, 4, 3]);
+
+        mesh.weld(1e-3);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn vertices_shared_with_a_mirrored_triangle_are_split() {
+        // The second triangle's u runs towards -x, so it has the opposite handedness.
+        let mut mesh = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [2.0, 0.0]]);
+        mesh.generate_tangents();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        for &index in &mesh.indices[0..3] {
+            assert_close(mesh.vertices[index as usize].tangent, [1.0, 0.0, 0.0, 1.0]);
+        }
+        for &index in &mesh.indices[3..6] {
+            assert_close(mesh.vertices[index as usize].tangent, [-1.0, 0.0, 0.0, -1.0]);
+        }
+        assert_eq!(mesh.vertices[mesh.indices[0] as usize].position, mesh.vertices[mesh.indices[3] as usize].position);
+        assert_eq!(mesh.vertices[mesh.indices[2] as usize].position, mesh.vertices[mesh.indices[4] as usize].position);
+    }
+}
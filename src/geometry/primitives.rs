@@ -0,0 +1,413 @@
+//! Generators for common shapes. Every shape is centered on the origin with Y up, wound
+//! counter-clockwise when seen from outside, and carries normals, tangents and UVs.
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::geometry::mesh_data::MeshData;
+use crate::geometry::vector::{add, cross, normalize, scale, sub};
+use crate::geometry::vertex::Vertex;
+
+fn push_vertex(mesh: &mut MeshData, position: [f32; 3], normal: [f32; 3], tangent: [f32; 3], uv: [f32; 2]) -> u32 {
+    mesh.vertices.push(Vertex {
+        position,
+        normal,
+        tangent: [tangent[0], tangent[1], tangent[2], 1.0],
+        uv,
+        ..Vertex::default()
+    });
+    (mesh.vertices.len() - 1) as u32
+}
+
+/// Connects a grid of `rows` x `columns` vertices starting at `first`, laid out row by
+/// row with u growing along a row and v growing from row to row.
+fn push_grid_indices(mesh: &mut MeshData, first: u32, rows: u32, columns: u32, skip_first_row: bool, skip_last_row: bool) {
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let top_left = first + row * columns + column;
+            let bottom_left = top_left + columns;
+            if !(skip_last_row && row == rows - 2) {
+                mesh.indices.extend_from_slice(&[top_left, bottom_left, bottom_left + 1]);
+            }
+            if !(skip_first_row && row == 0) {
+                mesh.indices.extend_from_slice(&[top_left, bottom_left + 1, top_left + 1]);
+            }
+        }
+    }
+}
+
+/// A flat subdivided quad spanning `u_axis` and `v_axis` from `origin`, facing
+/// `cross(v_axis, u_axis)`.
+fn push_quad(mesh: &mut MeshData, origin: [f32; 3], u_axis: [f32; 3], v_axis: [f32; 3], u_segments: u32, v_segments: u32) {
+    let u_segments = u_segments.max(1);
+    let v_segments = v_segments.max(1);
+    let normal = normalize(cross(v_axis, u_axis));
+    let tangent = normalize(u_axis);
+    let first = mesh.vertices.len() as u32;
+
+    for j in 0..=v_segments {
+        let v = j as f32 / v_segments as f32;
+        for i in 0..=u_segments {
+            let u = i as f32 / u_segments as f32;
+            let position = add(origin, add(scale(u_axis, u), scale(v_axis, v)));
+            push_vertex(mesh, position, normal, tangent, [u, v]);
+        }
+    }
+    push_grid_indices(mesh, first, v_segments + 1, u_segments + 1, false, false);
+}
+
+/// An axis aligned cube with `size` long edges, each face split into
+/// `subdivisions` x `subdivisions` quads.
+pub fn cube(size: f32, subdivisions: u32) -> MeshData {
+    let half = size / 2.0;
+    let faces = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0])
+    ];
+
+    let mut mesh = MeshData::default();
+    for &(normal, tangent) in &faces {
+        let up = cross(normal, tangent);
+        let origin = scale(add(add(normal, scale(tangent, -1.0)), up), half);
+        push_quad(&mut mesh, origin, scale(tangent, size), scale(up, -size), subdivisions, subdivisions);
+    }
+    mesh
+}
+
+/// A plane on the XZ axes facing +Y.
+pub fn plane(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> MeshData {
+    let mut mesh = MeshData::default();
+    push_quad(
+        &mut mesh,
+        [-width / 2.0, 0.0, -depth / 2.0],
+        [width, 0.0, 0.0],
+        [0.0, 0.0, depth],
+        x_segments,
+        z_segments
+    );
+    mesh
+}
+
+/// Pushes rows of vertices revolving around the Y axis. Each row is given as
+/// (radius, height, v, normal in the XY half plane), u wraps once around.
+fn push_revolution(mesh: &mut MeshData, rows: &[(f32, f32, f32, [f32; 2])], segments: u32, closed_top: bool, closed_bottom: bool) {
+    let segments = segments.max(3);
+    let first = mesh.vertices.len() as u32;
+    for &(radius, y, v, normal) in rows {
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+            push_vertex(
+                mesh,
+                [radius * sin, y, radius * cos],
+                normalize([normal[0] * sin, normal[1], normal[0] * cos]),
+                [cos, 0.0, -sin],
+                [u, v]
+            );
+        }
+    }
+    push_grid_indices(mesh, first, rows.len() as u32, segments + 1, closed_top, closed_bottom);
+}
+
+/// A flat disc at height `y` facing up or down.
+fn push_cap(mesh: &mut MeshData, radius: f32, y: f32, segments: u32, up: bool) {
+    let segments = segments.max(3);
+    let normal = if up { [0.0, 1.0, 0.0] } else { [0.0, -1.0, 0.0] };
+    let v_sign = if up { 1.0 } else { -1.0 };
+    let tangent = [1.0, 0.0, 0.0];
+
+    let center = push_vertex(mesh, [0.0, y, 0.0], normal, tangent, [0.5, 0.5]);
+    for i in 0..=segments {
+        let (sin, cos) = (i as f32 / segments as f32 * 2.0 * PI).sin_cos();
+        push_vertex(
+            mesh,
+            [radius * sin, y, radius * cos],
+            normal,
+            tangent,
+            [0.5 + 0.5 * sin, 0.5 + 0.5 * v_sign * cos]
+        );
+    }
+    for i in 0..segments {
+        let current = center + 1 + i;
+        if up {
+            mesh.indices.extend_from_slice(&[center, current, current + 1]);
+        } else {
+            mesh.indices.extend_from_slice(&[center, current + 1, current]);
+        }
+    }
+}
+
+fn frustum(bottom_radius: f32, top_radius: f32, height: f32, segments: u32, height_segments: u32, caps: bool) -> MeshData {
+    let height_segments = height_segments.max(1);
+    let half = height / 2.0;
+    let normal = [height, bottom_radius - top_radius];
+
+    let rows: Vec<(f32, f32, f32, [f32; 2])> = (0..=height_segments)
+        .map(|j| {
+            let v = j as f32 / height_segments as f32;
+            let radius = top_radius + (bottom_radius - top_radius) * v;
+            (radius, half - height * v, v, normal)
+        })
+        .collect();
+
+    let mut mesh = MeshData::default();
+    push_revolution(&mut mesh, &rows, segments, top_radius == 0.0, bottom_radius == 0.0);
+    if caps && top_radius > 0.0 {
+        push_cap(&mut mesh, top_radius, half, segments, true);
+    }
+    if caps && bottom_radius > 0.0 {
+        push_cap(&mut mesh, bottom_radius, -half, segments, false);
+    }
+    mesh
+}
+
+/// A capped cylinder along the Y axis.
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    frustum(radius, radius, height, segments, height_segments, true)
+}
+
+/// A cone along the Y axis with its tip pointing up and a capped base.
+pub fn cone(radius: f32, height: f32, segments: u32, height_segments: u32) -> MeshData {
+    frustum(radius, 0.0, height, segments, height_segments, true)
+}
+
+/// A sphere made of `rings` latitude bands and `segments` longitude slices.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(2);
+    let rows: Vec<(f32, f32, f32, [f32; 2])> = (0..=rings)
+        .map(|j| {
+            let v = j as f32 / rings as f32;
+            let (sin, cos) = (v * PI).sin_cos();
+            (radius * sin, radius * cos, v, [sin, cos])
+        })
+        .collect();
+
+    let mut mesh = MeshData::default();
+    push_revolution(&mut mesh, &rows, segments, true, true);
+    mesh
+}
+
+/// A cylinder of `height` with hemispheres of `radius` on both ends, `rings` is the
+/// number of latitude bands per hemisphere.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let half = height / 2.0;
+    let length = PI * radius + height;
+
+    let mut rows = Vec::new();
+    for (offset, first_angle, arc_offset) in &[(half, 0.0, 0.0), (-half, PI / 2.0, height)] {
+        for j in 0..=rings {
+            let angle = first_angle + PI / 2.0 * j as f32 / rings as f32;
+            let (sin, cos) = angle.sin_cos();
+            let v = (radius * angle + arc_offset) / length;
+            rows.push((radius * sin, offset + radius * cos, v, [sin, cos]));
+        }
+    }
+
+    let mut mesh = MeshData::default();
+    push_revolution(&mut mesh, &rows, segments, true, true);
+    mesh
+}
+
+/// A torus lying in the XZ plane. `major_radius` is the distance from the center to the
+/// middle of the tube, `minor_radius` the radius of the tube.
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let major_segments = major_segments.max(3);
+    let minor_segments = minor_segments.max(3);
+
+    let mut mesh = MeshData::default();
+    for j in 0..=minor_segments {
+        let v = j as f32 / minor_segments as f32;
+        let (tube_sin, tube_cos) = (v * 2.0 * PI).sin_cos();
+        for i in 0..=major_segments {
+            let u = i as f32 / major_segments as f32;
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+            let ring = major_radius + minor_radius * tube_cos;
+            push_vertex(
+                &mut mesh,
+                [ring * sin, -minor_radius * tube_sin, ring * cos],
+                [tube_cos * sin, -tube_sin, tube_cos * cos],
+                [cos, 0.0, -sin],
+                [u, v]
+            );
+        }
+    }
+    push_grid_indices(&mut mesh, 0, minor_segments + 1, major_segments + 1, false, false);
+    mesh
+}
+
+/// Adds a point of the unit sphere scaled to `radius`, with v and the tangent following
+/// from its latitude and `u`.
+fn push_sphere_vertex(mesh: &mut MeshData, radius: f32, direction: [f32; 3], u: f32) -> u32 {
+    let v = direction[1].clamp(-1.0, 1.0).acos() / PI;
+    let (sin, cos) = ((u - 0.5) * 2.0 * PI).sin_cos();
+    push_vertex(mesh, scale(direction, radius), direction, [cos, 0.0, -sin], [u, v])
+}
+
+/// A sphere made by repeatedly subdividing an icosahedron, giving evenly sized
+/// triangles. Triangles on the UV seam are cut along it so textures wrap cleanly with
+/// any address mode.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut positions: Vec<[f32; 3]> = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0]
+    ].iter().map(|&p| normalize(p)).collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1]
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, positions: &mut Vec<[f32; 3]>| -> u32 {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                positions.push(normalize(scale(add(positions[a as usize], positions[b as usize]), 0.5)));
+                (positions.len() - 1) as u32
+            })
+        };
+
+        let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+        for &[a, b, c] in &triangles {
+            let ab = midpoint(a, b, &mut positions);
+            let bc = midpoint(b, c, &mut positions);
+            let ca = midpoint(c, a, &mut positions);
+            subdivided.push([a, ab, ca]);
+            subdivided.push([b, bc, ab]);
+            subdivided.push([c, ca, bc]);
+            subdivided.push([ab, bc, ca]);
+        }
+        triangles = subdivided;
+    }
+
+    let mut mesh = MeshData::default();
+    for &position in &positions {
+        let u = 0.5 + position[0].atan2(position[2]) / (2.0 * PI);
+        push_sphere_vertex(&mut mesh, radius, position, u);
+    }
+
+    // The seam is the half plane x = 0, z < 0. Triangles crossing it are cut along it, the
+    // part on the +x side ends at u = 1 and the part on the -x side starts at u = 0, so
+    // texture coordinates never leave 0..1.
+    let mut seam_vertices: HashMap<(u32, u32, bool), u32> = HashMap::new();
+    let mut cut_triangles = Vec::with_capacity(triangles.len());
+    for triangle in &triangles {
+        let corners = [
+            mesh.vertices[triangle[0] as usize].normal,
+            mesh.vertices[triangle[1] as usize].normal,
+            mesh.vertices[triangle[2] as usize].normal
+        ];
+        let on_seam = (0..3).any(|corner| {
+            let (a, b) = (corners[corner], corners[(corner + 1) % 3]);
+            let crosses = a[0] * b[0] < 0.0 && a[2] + (b[2] - a[2]) * a[0] / (a[0] - b[0]) < 0.0;
+            crosses || (a[0] == 0.0 && a[2] < 0.0)
+        });
+        if !on_seam {
+            cut_triangles.push(*triangle);
+            continue;
+        }
+
+        for &positive in &[true, false] {
+            let seam_u = if positive { 1.0 } else { 0.0 };
+            let side = |x: f32| if positive { x > 0.0 } else { x < 0.0 };
+            let mut polygon = Vec::with_capacity(4);
+            for corner in 0..3 {
+                let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+                let (a_position, b_position) = (corners[corner], corners[(corner + 1) % 3]);
+                if side(a_position[0]) {
+                    polygon.push(a);
+                } else if a_position[0] == 0.0 {
+                    // On the seam itself, it needs the u of this side.
+                    let index = if mesh.vertices[a as usize].uv[0] == seam_u {
+                        a
+                    } else {
+                        *seam_vertices.entry((a, a, positive)).or_insert_with(|| {
+                            push_sphere_vertex(&mut mesh, radius, a_position, seam_u)
+                        })
+                    };
+                    polygon.push(index);
+                }
+                if a_position[0] * b_position[0] < 0.0 {
+                    let key = (a.min(b), a.max(b), positive);
+                    let index = *seam_vertices.entry(key).or_insert_with(|| {
+                        let t = a_position[0] / (a_position[0] - b_position[0]);
+                        let mut position = add(a_position, scale(sub(b_position, a_position), t));
+                        position[0] = 0.0;
+                        push_sphere_vertex(&mut mesh, radius, normalize(position), seam_u)
+                    });
+                    polygon.push(index);
+                }
+            }
+            for i in 1..polygon.len().saturating_sub(1) {
+                cut_triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+            }
+        }
+    }
+    let mut triangles = cut_triangles;
+
+    // Pole vertices have no meaningful u, every triangle touching one gets its own copy
+    // centered between the triangle's other two vertices.
+    for triangle in &mut triangles {
+        for corner in 0..3 {
+            let pole = mesh.vertices[triangle[corner] as usize];
+            if pole.position[0] != 0.0 || pole.position[2] != 0.0 {
+                continue;
+            }
+            let u = (mesh.vertices[triangle[(corner + 1) % 3] as usize].uv[0]
+                + mesh.vertices[triangle[(corner + 2) % 3] as usize].uv[0]) / 2.0;
+            let (sin, cos) = ((u - 0.5) * 2.0 * PI).sin_cos();
+            let mut copy = pole;
+            copy.uv[0] = u;
+            copy.tangent = [cos, 0.0, -sin, 1.0];
+            mesh.vertices.push(copy);
+            triangle[corner] = (mesh.vertices.len() - 1) as u32;
+        }
+    }
+
+    for triangle in &triangles {
+        mesh.indices.extend_from_slice(triangle);
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vector::{dot, length};
+
+    #[test]
+    fn icosphere_uvs_stay_in_range_across_the_seam() {
+        let radius = 2.0;
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let edge = 2.0 * radius / (1.0 + t * t).sqrt();
+        let mut previous_area = 20.0 * 3.0f32.sqrt() / 4.0 * edge * edge;
+        for subdivisions in 0..4 {
+            let mesh = icosphere(radius, subdivisions);
+            let mut area = 0.0;
+            for triangle in mesh.indices.chunks(3) {
+                let corners: Vec<&Vertex> = triangle.iter().map(|&i| &mesh.vertices[i as usize]).collect();
+                let us: Vec<f32> = corners.iter().map(|vertex| vertex.uv[0]).collect();
+                assert!(us.iter().all(|&u| (0.0..=1.0).contains(&u)));
+                assert!(us.iter().cloned().fold(0.0, f32::max) - us.iter().cloned().fold(1.0, f32::min) <= 0.5 + 1e-6);
+
+                let normal = cross(sub(corners[1].position, corners[0].position), sub(corners[2].position, corners[0].position));
+                assert!(dot(normal, corners[0].position) >= 0.0);
+                area += length(normal) / 2.0;
+            }
+
+            // A hole left by cutting would make the surface shrink instead of growing
+            // towards the sphere's.
+            assert!(area > previous_area - 1e-3);
+            assert!(area < 4.0 * PI * radius * radius);
+            previous_area = area;
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Mesh simplification with the quadric error metric, and level of detail chains built
+//! from it.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::geometry::mesh_data::{Bounds, MeshData};
+use crate::geometry::vector::{cross, dot, length, normalize, sub};
+use crate::math::mat4::Mat4;
+
+/// Weight of the planes holding borders and UV seams in place, relative to the surface.
+const BORDER_WEIGHT: f64 = 10.0;
+
+/// Collapses may not turn a triangle further than this, given as the cosine of the angle.
+const MIN_NORMAL_DOT: f32 = 0.2;
+
+/// The symmetric 4x4 matrix summing squared distances to a set of planes, upper triangle
+/// stored row by row.
+#[derive(Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: [f32; 3], point: [f32; 3], weight: f64) -> Quadric {
+        let (a, b, c) = (normal[0] as f64, normal[1] as f64, normal[2] as f64);
+        let d = -(a * point[0] as f64 + b * point[1] as f64 + c * point[2] as f64);
+        let mut values = [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d];
+        for value in values.iter_mut() {
+            *value *= weight;
+        }
+        Quadric(values)
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, point: [f32; 3]) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (point[0] as f64, point[1] as f64, point[2] as f64);
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum PositionKind {
+    /// Inside the surface, may collapse onto any neighbour.
+    Free,
+    /// On a border or a UV seam, may only slide along it.
+    Chain,
+    /// Where borders or seams meet, or the surface is non-manifold.
+    Locked
+}
+
+fn sorted(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+fn triangle_edges(triangle: [u32; 3]) -> [(u32, u32); 3] {
+    [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])]
+}
+
+/// Position level edges with the number of triangles using them, and whether the
+/// triangles on the two sides use different vertices, which makes the edge a UV seam.
+fn collect_edges(triangles: &[[u32; 3]], vertex_position: &[u32]) -> HashMap<(u32, u32), (u32, bool)> {
+    let mut vertex_edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for &triangle in triangles {
+        for &(a, b) in &triangle_edges(triangle) {
+            *vertex_edges.entry(sorted(a, b)).or_insert(0) += 1;
+        }
+    }
+
+    let mut edges: HashMap<(u32, u32), (u32, bool)> = HashMap::new();
+    for &triangle in triangles {
+        for &(a, b) in &triangle_edges(triangle) {
+            let edge = edges.entry(sorted(vertex_position[a as usize], vertex_position[b as usize])).or_insert((0, false));
+            edge.0 += 1;
+            edge.1 |= vertex_edges[&sorted(a, b)] == 1;
+        }
+    }
+    edges
+}
+
+fn is_constrained(count: u32, seam: bool) -> bool {
+    count != 2 || seam
+}
+
+struct Simplifier {
+    positions: Vec<[f32; 3]>,
+    vertex_position: Vec<u32>,
+    quadrics: Vec<Quadric>,
+    triangles: Vec<[u32; 3]>,
+    removed: Vec<bool>,
+    position_triangles: Vec<Vec<usize>>
+}
+
+impl Simplifier {
+    fn position_of(&self, vertex: u32) -> u32 {
+        self.vertex_position[vertex as usize]
+    }
+
+    fn live_triangles(&self, position: u32) -> Vec<usize> {
+        self.position_triangles[position as usize].iter()
+            .cloned()
+            .filter(|&triangle| !self.removed[triangle])
+            .collect()
+    }
+
+    fn neighbours(&self, position: u32, triangles: &[usize]) -> HashSet<u32> {
+        triangles.iter()
+            .flat_map(|&triangle| self.triangles[triangle].iter())
+            .map(|&vertex| self.position_of(vertex))
+            .filter(|&other| other != position)
+            .collect()
+    }
+
+    /// Moves every vertex at position `from` onto the matching vertex at `to`. Returns the
+    /// positions that were next to `from` and the number of triangles removed, or `None`
+    /// when the collapse would tear a seam, change the topology or flip a triangle.
+    fn collapse(&mut self, from: u32, to: u32) -> Option<(HashSet<u32>, usize)> {
+        let around = self.live_triangles(from);
+
+        // Every vertex at `from` has to share triangles with exactly one vertex at `to`,
+        // so the attributes on each side of a seam stay on their side.
+        let mut mapping: HashMap<u32, u32> = HashMap::new();
+        for &triangle in &around {
+            let corners = self.triangles[triangle];
+            let source = corners.iter().cloned().find(|&vertex| self.position_of(vertex) == from);
+            let target = corners.iter().cloned().find(|&vertex| self.position_of(vertex) == to);
+            if let (Some(source), Some(target)) = (source, target) {
+                if *mapping.entry(source).or_insert(target) != target {
+                    return None;
+                }
+            }
+        }
+        for &triangle in &around {
+            let mapped = self.triangles[triangle].iter()
+                .all(|&vertex| self.position_of(vertex) != from || mapping.contains_key(&vertex));
+            if !mapped {
+                return None;
+            }
+        }
+
+        // The link condition: the only neighbours the two positions share are the ones
+        // across the collapsed edge, otherwise the surface would pinch.
+        let neighbours = self.neighbours(from, &around);
+        let target_neighbours = self.neighbours(to, &self.live_triangles(to));
+        let shared_triangles = around.iter()
+            .filter(|&&triangle| self.triangles[triangle].iter().any(|&vertex| self.position_of(vertex) == to))
+            .count();
+        if neighbours.intersection(&target_neighbours).count() != shared_triangles {
+            return None;
+        }
+
+        for &triangle in &around {
+            let corners = self.triangles[triangle];
+            if corners.iter().any(|&vertex| self.position_of(vertex) == to) {
+                continue;
+            }
+            let before = corners.iter().map(|&vertex| self.positions[self.position_of(vertex) as usize]).collect::<Vec<_>>();
+            let after = corners.iter()
+                .map(|&vertex| {
+                    let position = self.position_of(vertex);
+                    self.positions[if position == from { to } else { position } as usize]
+                })
+                .collect::<Vec<_>>();
+            let normal_before = cross(sub(before[1], before[0]), sub(before[2], before[0]));
+            let normal_after = cross(sub(after[1], after[0]), sub(after[2], after[0]));
+            if length(normal_before) > 0.0 && dot(normalize(normal_before), normalize(normal_after)) < MIN_NORMAL_DOT {
+                return None;
+            }
+        }
+
+        let mut removed = 0;
+        for &triangle in &around {
+            if self.triangles[triangle].iter().any(|&vertex| self.position_of(vertex) == to) {
+                self.removed[triangle] = true;
+                removed += 1;
+                continue;
+            }
+            for vertex in self.triangles[triangle].iter_mut() {
+                if let Some(&target) = mapping.get(vertex) {
+                    *vertex = target;
+                }
+            }
+            self.position_triangles[to as usize].push(triangle);
+        }
+        let source = self.quadrics[from as usize];
+        self.quadrics[to as usize].add(&source);
+        Some((neighbours, removed))
+    }
+
+    /// One round of collapses, cheapest first, each touching a part of the mesh no other
+    /// collapse of the round has changed. Returns the number of triangles removed.
+    fn pass(&mut self, max_removed: usize) -> usize {
+        let position_count = self.positions.len();
+        let edges = collect_edges(&self.triangles, &self.vertex_position);
+        self.removed = vec![false; self.triangles.len()];
+        self.position_triangles = vec![Vec::new(); position_count];
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            for &vertex in triangle {
+                self.position_triangles[self.vertex_position[vertex as usize] as usize].push(index);
+            }
+        }
+
+        let mut constrained_edges = vec![0u32; position_count];
+        let mut non_manifold = vec![false; position_count];
+        for (&(a, b), &(count, seam)) in &edges {
+            if is_constrained(count, seam) {
+                constrained_edges[a as usize] += 1;
+                constrained_edges[b as usize] += 1;
+            }
+            if count > 2 {
+                non_manifold[a as usize] = true;
+                non_manifold[b as usize] = true;
+            }
+        }
+        let kinds: Vec<PositionKind> = (0..position_count)
+            .map(|position| match (non_manifold[position], constrained_edges[position]) {
+                (false, 0) => PositionKind::Free,
+                (false, 2) => PositionKind::Chain,
+                _ => PositionKind::Locked
+            })
+            .collect();
+
+        let mut candidates: Vec<(f64, u32, u32)> = Vec::new();
+        for (&(a, b), &(count, seam)) in &edges {
+            for &(from, to) in &[(a, b), (b, a)] {
+                let allowed = match kinds[from as usize] {
+                    PositionKind::Free => true,
+                    PositionKind::Chain => is_constrained(count, seam),
+                    PositionKind::Locked => false
+                };
+                if allowed {
+                    let mut quadric = self.quadrics[from as usize];
+                    quadric.add(&self.quadrics[to as usize]);
+                    candidates.push((quadric.error(self.positions[to as usize]), from, to));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2))
+        });
+
+        let mut touched = vec![false; position_count];
+        let mut removed = 0;
+        for &(_, from, to) in &candidates {
+            if removed >= max_removed {
+                break;
+            }
+            if touched[from as usize] || touched[to as usize] {
+                continue;
+            }
+            if let Some((neighbours, count)) = self.collapse(from, to) {
+                removed += count;
+                touched[from as usize] = true;
+                for neighbour in neighbours {
+                    touched[neighbour as usize] = true;
+                }
+            }
+        }
+
+        let removed_flags = &self.removed;
+        let mut index = 0;
+        self.triangles.retain(|_| {
+            index += 1;
+            !removed_flags[index - 1]
+        });
+        removed
+    }
+}
+
+/// Reduces the mesh to about `target_ratio` of its triangles by collapsing the edges with
+/// the smallest quadric error. Vertices keep their position and attributes, collapses move
+/// one onto a neighbour.
+///
+/// Borders and UV seams are preserved: vertices on them only slide along the border or
+/// seam and vertices where several meet never move. The result can have more triangles than
+/// asked for when nothing else can be collapsed safely.
+pub fn simplify(mesh: &MeshData, target_ratio: f32) -> MeshData {
+    let mut position_ids: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let vertex_position: Vec<u32> = mesh.vertices.iter()
+        .map(|vertex| {
+            let p = vertex.position;
+            *position_ids.entry([p[0].to_bits(), p[1].to_bits(), p[2].to_bits()]).or_insert_with(|| {
+                positions.push(p);
+                (positions.len() - 1) as u32
+            })
+        })
+        .collect();
+
+    let triangles: Vec<[u32; 3]> = mesh.indices.chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .filter(|triangle| {
+            let a = vertex_position[triangle[0] as usize];
+            let b = vertex_position[triangle[1] as usize];
+            let c = vertex_position[triangle[2] as usize];
+            a != b && b != c && c != a
+        })
+        .collect();
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let edges = collect_edges(&triangles, &vertex_position);
+    for &triangle in &triangles {
+        let corners = [
+            vertex_position[triangle[0] as usize],
+            vertex_position[triangle[1] as usize],
+            vertex_position[triangle[2] as usize]
+        ];
+        let [a, b, c] = [positions[corners[0] as usize], positions[corners[1] as usize], positions[corners[2] as usize]];
+        let normal = cross(sub(b, a), sub(c, a));
+        let area = length(normal) as f64 / 2.0;
+        let normal = normalize(normal);
+        let face = Quadric::from_plane(normal, a, area);
+        for &corner in &corners {
+            quadrics[corner as usize].add(&face);
+        }
+
+        // Constrained edges get a plane perpendicular to the surface through them.
+        for &(start, end) in &triangle_edges(corners) {
+            let (count, seam) = edges[&sorted(start, end)];
+            if !is_constrained(count, seam) {
+                continue;
+            }
+            let edge = sub(positions[end as usize], positions[start as usize]);
+            let side = normalize(cross(edge, normal));
+            let border = Quadric::from_plane(side, positions[start as usize], dot(edge, edge) as f64 * BORDER_WEIGHT);
+            quadrics[start as usize].add(&border);
+            quadrics[end as usize].add(&border);
+        }
+    }
+
+    let target = (triangles.len() as f32 * target_ratio.clamp(0.0, 1.0)).round() as usize;
+    let mut simplifier = Simplifier {
+        positions,
+        vertex_position,
+        quadrics,
+        triangles,
+        removed: Vec::new(),
+        position_triangles: Vec::new()
+    };
+    while simplifier.triangles.len() > target {
+        let max_removed = simplifier.triangles.len() - target;
+        if simplifier.pass(max_removed) == 0 {
+            break;
+        }
+    }
+
+    let mut remap = vec![u32::MAX; mesh.vertices.len()];
+    let mut result = MeshData::default();
+    for triangle in &simplifier.triangles {
+        for &vertex in triangle {
+            if remap[vertex as usize] == u32::MAX {
+                remap[vertex as usize] = result.vertices.len() as u32;
+                result.vertices.push(mesh.vertices[vertex as usize]);
+            }
+            result.indices.push(remap[vertex as usize]);
+        }
+    }
+    result
+}
+
+/// The fraction of the screen height covered by `bounds` when seen through `model_view`
+/// and a perspective `projection`. Returns infinity when the camera is inside the bounds.
+pub fn screen_size(bounds: &Bounds, model_view: &Mat4, projection: &Mat4) -> f32 {
+    let center = bounds.get_center();
+    let view_z = (0..3).map(|i| model_view.get(2, i) * center[i]).sum::<f32>() + model_view.get(2, 3);
+    let scale = (0..3)
+        .map(|col| length([model_view.get(0, col), model_view.get(1, col), model_view.get(2, col)]))
+        .fold(0.0, f32::max);
+    let radius = bounds.get_radius() * scale;
+    let distance = -view_z;
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    radius * projection.get(1, 1) / distance
+}
+
+/// A mesh together with progressively simplified versions of it.
+pub struct LodChain {
+    /// `levels[0]` is the source mesh, later levels have fewer triangles.
+    pub levels: Vec<MeshData>,
+    /// The smallest screen size each level is drawn at, see `screen_size`.
+    pub screen_sizes: Vec<f32>,
+    pub bounds: Bounds
+}
+
+impl LodChain {
+    /// Builds a level for each ratio of the source triangle count, each simplified from the
+    /// one before. A level is drawn while the mesh covers at least half its ratio of the
+    /// screen height, change `screen_sizes` to tune this.
+    pub fn new(mesh: &MeshData, ratios: &[f32]) -> LodChain {
+        let source_triangles = (mesh.indices.len() / 3).max(1) as f32;
+        let mut levels = vec![mesh.clone()];
+        let mut screen_sizes = vec![0.5];
+        for &ratio in ratios {
+            let previous = &levels[levels.len() - 1];
+            let previous_triangles = (previous.indices.len() / 3).max(1) as f32;
+            let level = simplify(previous, ratio * source_triangles / previous_triangles);
+            levels.push(level);
+            screen_sizes.push(ratio / 2.0);
+        }
+
+        LodChain {
+            levels,
+            screen_sizes,
+            bounds: mesh.compute_bounds().unwrap_or(Bounds {
+                min: [0.0; 3],
+                max: [0.0; 3]
+            })
+        }
+    }
+
+    /// The index of the level to draw at the given screen size.
+    pub fn select(&self, screen_size: f32) -> usize {
+        self.screen_sizes.iter()
+            .position(|&min| screen_size >= min)
+            .unwrap_or(self.levels.len() - 1)
+    }
+
+    /// The index of the level to draw for the mesh seen through `model_view` and `projection`.
+    pub fn select_for_view(&self, model_view: &Mat4, projection: &Mat4) -> usize {
+        self.select(screen_size(&self.bounds, model_view, projection))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::primitives::{icosphere, uv_sphere};
+
+    #[test]
+    fn simplify_reaches_the_target_on_a_sphere() {
+        for mesh in &[uv_sphere(1.0, 32, 16), icosphere(1.0, 3)] {
+            let triangle_count = mesh.indices.len() / 3;
+            for &ratio in &[0.5, 0.25, 0.1] {
+                let simplified = simplify(mesh, ratio);
+                let target = (triangle_count as f32 * ratio).round() as usize;
+                assert!(simplified.indices.len() / 3 <= target, "{} triangles for a target of {}", simplified.indices.len() / 3, target);
+                assert!(simplified.indices.len() / 3 >= target / 2);
+                for triangle in simplified.indices.chunks(3) {
+                    assert!(triangle.iter().all(|&index| (index as usize) < simplified.vertices.len()));
+                    assert!(triangle[0] != triangle[1] && triangle[1] != triangle[2] && triangle[2] != triangle[0]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// The engine's CPU-side vertex with every attribute loaders and generators can produce.
+/// Attributes a source doesn't provide keep their default value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// xyz is the tangent direction, w the handedness of the bitangent (1.0 or -1.0).
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+    pub color: [f32; 4]
+}
+
+impl Default for Vertex {
+    fn default() -> Vertex {
+        Vertex {
+            position: [0.0, 0.0, 0.0],
+            normal: [0.0, 0.0, 0.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+            uv: [0.0, 0.0],
+            color: [1.0, 1.0, 1.0, 1.0]
+        }
+    }
+}
+
+/// Maps the engine's `Vertex` to a user vertex type, so loaded geometry can be uploaded
+/// into a `Mesh<V>`.
+pub trait FromVertex {
+    fn from_vertex(vertex: &Vertex) -> Self;
+}
+
+impl FromVertex for Vertex {
+    fn from_vertex(vertex: &Vertex) -> Vertex {
+        *vertex
+    }
+}
@@ -1,5 +1,7 @@
 pub mod renderer;
 pub mod math;
+pub mod geometry;
+pub mod loader;
 pub use wgpu;
 pub use shaderc::ShaderKind as ShaderKind;
 pub use winit;
\ No newline at end of file
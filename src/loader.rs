@@ -0,0 +1,50 @@
+pub mod bc;
+pub mod dds;
+pub mod gltf;
+pub mod hdr;
+pub mod ktx2;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+pub mod texture_image;
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Parse(String)
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "IO error: {}", error),
+            LoadError::Image(error) => write!(f, "Image error: {}", error),
+            LoadError::Parse(message) => write!(f, "Parse error: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(error) => Some(error),
+            LoadError::Image(error) => Some(error),
+            LoadError::Parse(_) => None
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(error: std::io::Error) -> LoadError {
+        LoadError::Io(error)
+    }
+}
+
+impl From<image::ImageError> for LoadError {
+    fn from(error: image::ImageError) -> LoadError {
+        LoadError::Image(error)
+    }
+}
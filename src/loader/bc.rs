@@ -0,0 +1,709 @@
+//! CPU decoders for block compressed formats. wgpu doesn't expose BC texture formats yet, so
+//! compressed textures are expanded to the nearest uncompressed format when they're loaded.
+
+use std::convert::TryInto;
+
+/// A block compression format, storing 4x4 pixel blocks in 8 or 16 bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockFormat {
+    /// RGB with 1-bit alpha, also known as DXT1.
+    Bc1,
+    /// RGB with explicit 4-bit alpha, also known as DXT3.
+    Bc2,
+    /// RGB with interpolated alpha, also known as DXT5.
+    Bc3,
+    /// One channel, also known as ATI1.
+    Bc4,
+    /// Two channels, also known as ATI2, mostly used for normal maps.
+    Bc5,
+    /// Unsigned half float RGB, for HDR images.
+    Bc6hUfloat,
+    /// Signed half float RGB.
+    Bc6hSfloat,
+    /// High quality RGBA.
+    Bc7
+}
+
+impl BlockFormat {
+    /// The size of one 4x4 block in bytes.
+    pub fn block_size(self) -> usize {
+        match self {
+            BlockFormat::Bc1 | BlockFormat::Bc4 => 8,
+            _ => 16
+        }
+    }
+
+    /// The size of a compressed image in bytes. Partial blocks at the edges are stored whole.
+    pub fn compressed_size(self, width: u32, height: u32) -> usize {
+        (width as usize).div_ceil(4) * (height as usize).div_ceil(4) * self.block_size()
+    }
+
+    /// The uncompressed format `decompress` produces. `srgb` only matters for the 8-bit color
+    /// formats, BC4, BC5 and BC6H are always linear.
+    pub fn decoded_format(self, srgb: bool) -> wgpu::TextureFormat {
+        match self {
+            BlockFormat::Bc4 => wgpu::TextureFormat::R8Unorm,
+            BlockFormat::Bc5 => wgpu::TextureFormat::Rg8Unorm,
+            BlockFormat::Bc6hUfloat | BlockFormat::Bc6hSfloat => wgpu::TextureFormat::Rgba16Float,
+            _ if srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            _ => wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+
+    /// The size of a decoded pixel in bytes.
+    fn decoded_pixel_size(self) -> usize {
+        match self {
+            BlockFormat::Bc4 => 1,
+            BlockFormat::Bc5 => 2,
+            BlockFormat::Bc6hUfloat | BlockFormat::Bc6hSfloat => 8,
+            _ => 4
+        }
+    }
+}
+
+/// Decodes a compressed image into tightly packed pixels of `format.decoded_format()`.
+///
+/// # Panics
+///
+/// Panics if `data` is shorter than `format.compressed_size(width, height)`.
+pub fn decompress(format: BlockFormat, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let pixel_size = format.decoded_pixel_size();
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+    let mut result = vec![0; width * height * pixel_size];
+
+    for block_y in 0..blocks_y {
+        for block_x in 0..blocks_x {
+            let offset = (block_y * blocks_x + block_x) * format.block_size();
+            let block = &data[offset..offset + format.block_size()];
+            let pixels = match format {
+                BlockFormat::Bc6hUfloat | BlockFormat::Bc6hSfloat => {
+                    decode_bc6h(block, format == BlockFormat::Bc6hSfloat)
+                },
+                _ => decode_ldr(format, block).map(|[r, g, b, a]| [r, g, b, a, 0, 0, 0, 0])
+            };
+
+            for (i, pixel) in pixels.iter().enumerate() {
+                let x = block_x * 4 + i % 4;
+                let y = block_y * 4 + i / 4;
+                if x < width && y < height {
+                    let offset = (y * width + x) * pixel_size;
+                    result[offset..offset + pixel_size].copy_from_slice(&pixel[..pixel_size]);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Decodes one block of an 8-bit format into RGBA pixels, channels the format doesn't
+/// store are left at zero.
+fn decode_ldr(format: BlockFormat, block: &[u8]) -> [[u8; 4]; 16] {
+    match format {
+        BlockFormat::Bc1 => decode_color(block, false),
+        BlockFormat::Bc2 => {
+            let mut pixels = decode_color(&block[8..], true);
+            let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                pixel[3] = ((alpha >> (i * 4)) & 0xf) as u8 * 17;
+            }
+            pixels
+        },
+        BlockFormat::Bc3 => {
+            let mut pixels = decode_color(&block[8..], true);
+            for (pixel, alpha) in pixels.iter_mut().zip(decode_alpha(&block[..8]).iter()) {
+                pixel[3] = *alpha;
+            }
+            pixels
+        },
+        BlockFormat::Bc4 => {
+            let mut pixels = [[0; 4]; 16];
+            for (pixel, red) in pixels.iter_mut().zip(decode_alpha(block).iter()) {
+                pixel[0] = *red;
+            }
+            pixels
+        },
+        BlockFormat::Bc5 => {
+            let mut pixels = [[0; 4]; 16];
+            let red = decode_alpha(&block[..8]);
+            let green = decode_alpha(&block[8..]);
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                pixel[0] = red[i];
+                pixel[1] = green[i];
+            }
+            pixels
+        },
+        BlockFormat::Bc7 => decode_bc7(block),
+        BlockFormat::Bc6hUfloat | BlockFormat::Bc6hSfloat => unreachable!("BC6H decodes to half floats")
+    }
+}
+
+fn rgb565(value: u16) -> [u8; 4] {
+    let red = (value >> 11) as u8 & 0x1f;
+    let green = (value >> 5) as u8 & 0x3f;
+    let blue = value as u8 & 0x1f;
+    [red << 3 | red >> 2, green << 2 | green >> 4, blue << 3 | blue >> 2, 255]
+}
+
+/// Decodes the BC1 color block shared by BC1, BC2 and BC3. Only BC1 has the three color
+/// mode with transparent black, the others always interpolate four colors.
+fn decode_color(block: &[u8], always_four_colors: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+
+    let (first, second) = (rgb565(color0), rgb565(color1));
+    let mut colors = [first, second, [0; 4], [0; 4]];
+    for channel in 0..3 {
+        let (a, b) = (first[channel] as u32, second[channel] as u32);
+        if color0 > color1 || always_four_colors {
+            colors[2][channel] = ((2 * a + b + 1) / 3) as u8;
+            colors[3][channel] = ((a + 2 * b + 1) / 3) as u8;
+        } else {
+            colors[2][channel] = (a + b).div_ceil(2) as u8;
+        }
+    }
+    colors[2][3] = 255;
+    if color0 > color1 || always_four_colors {
+        colors[3][3] = 255;
+    }
+
+    let mut pixels = [[0; 4]; 16];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = colors[((indices >> (i * 2)) & 3) as usize];
+    }
+    pixels
+}
+
+/// Decodes the interpolated single channel block used for BC3 alpha, BC4 and BC5.
+fn decode_alpha(block: &[u8]) -> [u8; 16] {
+    let (first, second) = (block[0] as u32, block[1] as u32);
+    let mut values = [first, second, 0, 0, 0, 0, 0, 255];
+    if first > second {
+        for i in 1..7 {
+            values[i + 1] = ((7 - i as u32) * first + i as u32 * second + 3) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            values[i + 1] = ((5 - i as u32) * first + i as u32 * second + 2) / 5;
+        }
+    }
+
+    let mut bits = [0; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    let mut result = [0; 16];
+    for (i, value) in result.iter_mut().enumerate() {
+        *value = values[((indices >> (i * 3)) & 7) as usize] as u8;
+    }
+    result
+}
+
+/// The layout of one of the eight BC7 block modes.
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// One p-bit per endpoint, appended as the lowest bit of every channel.
+    endpoint_pbits: bool,
+    /// One p-bit per subset, shared by both of its endpoints.
+    shared_pbits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 }
+];
+
+/// Two subset partitions, bit `i` is set when pixel `i` belongs to the second subset.
+const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22
+];
+
+/// Three subset partitions, two bits per pixel holding its subset.
+const PARTITIONS_3: [u32; 64] = [
+    0xaa68_5050, 0x6a5a_5040, 0x5a5a_4200, 0x5450_a0a8, 0xa5a5_0000, 0xa0a0_5050, 0x5555_a0a0, 0x5a5a_5050,
+    0xaa55_0000, 0xaa55_5500, 0xaaaa_5500, 0x9090_9090, 0x9494_9494, 0xa4a4_a4a4, 0xa9a5_9450, 0x2a0a_4250,
+    0xa594_5040, 0x0a42_5054, 0xa5a5_a500, 0x55a0_a0a0, 0xa8a8_5454, 0x6a6a_4040, 0xa4a4_5000, 0x1a1a_0500,
+    0x0050_a4a4, 0xaaa5_9090, 0x1469_6914, 0x6969_1400, 0xa085_85a0, 0xaa82_1414, 0x50a4_a450, 0x6a5a_0200,
+    0xa9a5_8000, 0x5090_a0a8, 0xa8a0_9050, 0x2424_2424, 0x00aa_5500, 0x2492_4924, 0x2449_9224, 0x50a5_0a50,
+    0x500a_a550, 0xaaaa_4444, 0x6666_0000, 0xa5a0_a5a0, 0x50a0_50a0, 0x6928_6928, 0x44aa_aa44, 0x6666_6600,
+    0xaa44_4444, 0x54a8_54a8, 0x9580_9580, 0x9696_9600, 0xa854_54a8, 0x8095_9580, 0xaa14_1414, 0x9696_0000,
+    0xaaaa_1414, 0xa050_50a0, 0xa0a5_a5a0, 0x9600_0000, 0x4080_4080, 0xa9a8_a9a8, 0xaaaa_aa44, 0x2a4a_5254
+];
+
+/// The pixel of the second subset whose index is stored with one bit less, for two subsets.
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15
+];
+
+/// The anchor pixels of the second and third subset, for three subsets.
+const ANCHORS_3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+        3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+        8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+        3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+        15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+        15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+        15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8
+    ]
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Reads a block's bits from the lowest upwards.
+struct BitReader {
+    bits: u128,
+    position: u32
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = self.bits.checked_shr(self.position).unwrap_or(0) as u32 & ((1u64 << count) - 1) as u32;
+        self.position += count;
+        value
+    }
+}
+
+/// Expands a `bits` wide value to 8 bits by repeating its highest bits.
+fn unquantize(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    value | value >> bits
+}
+
+fn interpolate(first: u32, second: u32, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        _ => WEIGHTS_4[index as usize]
+    };
+    (((64 - weight) * first + weight * second + 32) >> 6) as u8
+}
+
+fn decode_bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(block.try_into().unwrap()),
+        position: 0
+    };
+    let mut mode_index = 0;
+    while mode_index < 8 && reader.read(1) == 0 {
+        mode_index += 1;
+    }
+    if mode_index == 8 {
+        // Reserved mode, decoders have to output transparent black.
+        return [[0; 4]; 16];
+    }
+    let mode = &BC7_MODES[mode_index];
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = reader.read(mode.color_bits);
+        }
+    }
+    if mode.alpha_bits > 0 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[3] = reader.read(mode.alpha_bits);
+        }
+    }
+
+    let mut pbits = [0u32; 6];
+    if mode.endpoint_pbits {
+        for pbit in pbits.iter_mut().take(endpoint_count) {
+            *pbit = reader.read(1);
+        }
+    } else if mode.shared_pbits {
+        for subset in 0..mode.subsets {
+            let pbit = reader.read(1);
+            pbits[subset * 2] = pbit;
+            pbits[subset * 2 + 1] = pbit;
+        }
+    }
+    let has_pbits = mode.endpoint_pbits || mode.shared_pbits;
+    let color_precision = mode.color_bits + has_pbits as u32;
+    let alpha_precision = mode.alpha_bits + has_pbits as u32;
+    for (endpoint, pbit) in endpoints.iter_mut().zip(pbits.iter()).take(endpoint_count) {
+        for channel in endpoint.iter_mut().take(3) {
+            *channel = unquantize(*channel << has_pbits as u32 | pbit, color_precision);
+        }
+        endpoint[3] = if mode.alpha_bits > 0 {
+            unquantize(endpoint[3] << has_pbits as u32 | pbit, alpha_precision)
+        } else {
+            255
+        };
+    }
+
+    let subset_of = |pixel: usize| -> usize {
+        match mode.subsets {
+            1 => 0,
+            2 => (PARTITIONS_2[partition] >> pixel) as usize & 1,
+            _ => (PARTITIONS_3[partition] >> (pixel * 2)) as usize & 3
+        }
+    };
+    let is_anchor = |pixel: usize| -> bool {
+        pixel == 0 || match mode.subsets {
+            1 => false,
+            2 => pixel == ANCHORS_2[partition] as usize,
+            _ => pixel == ANCHORS_3[0][partition] as usize || pixel == ANCHORS_3[1][partition] as usize
+        }
+    };
+
+    let mut indices = [0u32; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        *index = reader.read(mode.index_bits - is_anchor(pixel) as u32);
+    }
+    let mut secondary_indices = [0u32; 16];
+    if mode.secondary_index_bits > 0 {
+        for (pixel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = reader.read(mode.secondary_index_bits - (pixel == 0) as u32);
+        }
+    }
+
+    let mut pixels = [[0; 4]; 16];
+    for (pixel, color) in pixels.iter_mut().enumerate() {
+        let subset = subset_of(pixel);
+        let (first, second) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let (color_index, alpha_index) = if mode.secondary_index_bits == 0 {
+            ((indices[pixel], mode.index_bits), (indices[pixel], mode.index_bits))
+        } else if index_selection == 0 {
+            ((indices[pixel], mode.index_bits), (secondary_indices[pixel], mode.secondary_index_bits))
+        } else {
+            ((secondary_indices[pixel], mode.secondary_index_bits), (indices[pixel], mode.index_bits))
+        };
+        for channel in 0..3 {
+            color[channel] = interpolate(first[channel], second[channel], color_index.0, color_index.1);
+        }
+        color[3] = interpolate(first[3], second[3], alpha_index.0, alpha_index.1);
+        if rotation > 0 {
+            color.swap(rotation as usize - 1, 3);
+        }
+    }
+    pixels
+}
+
+// Endpoint channels as they're numbered in a BC6H layout, `endpoint * 3 + channel`.
+const R0: usize = 0;
+const G0: usize = 1;
+const B0: usize = 2;
+const R1: usize = 3;
+const G1: usize = 4;
+const B1: usize = 5;
+const R2: usize = 6;
+const G2: usize = 7;
+const B2: usize = 8;
+const R3: usize = 9;
+const G3: usize = 10;
+const B3: usize = 11;
+
+/// The layout of one of the fourteen BC6H block modes.
+struct Bc6hMode {
+    /// The value of the block's lowest 2 bits, or 5 bits if those are above 1.
+    value: u32,
+    subsets: usize,
+    endpoint_bits: u32,
+    /// The precision of the other endpoints, stored as deltas from the first one when
+    /// `transformed`.
+    delta_bits: [u32; 3],
+    transformed: bool,
+    /// Runs of endpoint bits as `(channel, first, last)`, in the order they're stored. Some
+    /// runs go from a higher bit down to a lower one.
+    layout: &'static [(usize, u32, u32)]
+}
+
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { value: 0, subsets: 2, endpoint_bits: 10, delta_bits: [5, 5, 5], transformed: true, layout: &[
+        (G2, 4, 4), (B2, 4, 4), (B3, 4, 4), (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 4), (G3, 4, 4),
+        (G2, 0, 3), (G1, 0, 4), (B3, 0, 0), (G3, 0, 3), (B1, 0, 4), (B3, 1, 1), (B2, 0, 3), (R2, 0, 4),
+        (B3, 2, 2), (R3, 0, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 1, subsets: 2, endpoint_bits: 7, delta_bits: [6, 6, 6], transformed: true, layout: &[
+        (G2, 5, 5), (G3, 4, 4), (G3, 5, 5), (R0, 0, 6), (B3, 0, 0), (B3, 1, 1), (B2, 4, 4), (G0, 0, 6),
+        (B2, 5, 5), (B3, 2, 2), (G2, 4, 4), (B0, 0, 6), (B3, 3, 3), (B3, 5, 5), (B3, 4, 4), (R1, 0, 5),
+        (G2, 0, 3), (G1, 0, 5), (G3, 0, 3), (B1, 0, 5), (B2, 0, 3), (R2, 0, 5), (R3, 0, 5)
+    ] },
+    Bc6hMode { value: 2, subsets: 2, endpoint_bits: 11, delta_bits: [5, 4, 4], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 4), (R0, 10, 10), (G2, 0, 3), (G1, 0, 3), (G0, 10, 10),
+        (B3, 0, 0), (G3, 0, 3), (B1, 0, 3), (B0, 10, 10), (B3, 1, 1), (B2, 0, 3), (R2, 0, 4), (B3, 2, 2),
+        (R3, 0, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 6, subsets: 2, endpoint_bits: 11, delta_bits: [4, 5, 4], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 3), (R0, 10, 10), (G3, 4, 4), (G2, 0, 3), (G1, 0, 4),
+        (G0, 10, 10), (G3, 0, 3), (B1, 0, 3), (B0, 10, 10), (B3, 1, 1), (B2, 0, 3), (R2, 0, 3), (B3, 0, 0),
+        (B3, 2, 2), (R3, 0, 3), (G2, 4, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 10, subsets: 2, endpoint_bits: 11, delta_bits: [4, 4, 5], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 3), (R0, 10, 10), (B2, 4, 4), (G2, 0, 3), (G1, 0, 3),
+        (G0, 10, 10), (B3, 0, 0), (G3, 0, 3), (B1, 0, 4), (B0, 10, 10), (B2, 0, 3), (R2, 0, 3), (B3, 1, 1),
+        (B3, 2, 2), (R3, 0, 3), (B3, 4, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 14, subsets: 2, endpoint_bits: 9, delta_bits: [5, 5, 5], transformed: true, layout: &[
+        (R0, 0, 8), (B2, 4, 4), (G0, 0, 8), (G2, 4, 4), (B0, 0, 8), (B3, 4, 4), (R1, 0, 4), (G3, 4, 4),
+        (G2, 0, 3), (G1, 0, 4), (B3, 0, 0), (G3, 0, 3), (B1, 0, 4), (B3, 1, 1), (B2, 0, 3), (R2, 0, 4),
+        (B3, 2, 2), (R3, 0, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 18, subsets: 2, endpoint_bits: 8, delta_bits: [6, 5, 5], transformed: true, layout: &[
+        (R0, 0, 7), (G3, 4, 4), (B2, 4, 4), (G0, 0, 7), (B3, 2, 2), (G2, 4, 4), (B0, 0, 7), (B3, 3, 3),
+        (B3, 4, 4), (R1, 0, 5), (G2, 0, 3), (G1, 0, 4), (B3, 0, 0), (G3, 0, 3), (B1, 0, 4), (B3, 1, 1),
+        (B2, 0, 3), (R2, 0, 5), (R3, 0, 5)
+    ] },
+    Bc6hMode { value: 22, subsets: 2, endpoint_bits: 8, delta_bits: [5, 6, 5], transformed: true, layout: &[
+        (R0, 0, 7), (B3, 0, 0), (B2, 4, 4), (G0, 0, 7), (G2, 5, 5), (G2, 4, 4), (B0, 0, 7), (G3, 5, 5),
+        (B3, 4, 4), (R1, 0, 4), (G3, 4, 4), (G2, 0, 3), (G1, 0, 5), (G3, 0, 3), (B1, 0, 4), (B3, 1, 1),
+        (B2, 0, 3), (R2, 0, 4), (B3, 2, 2), (R3, 0, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 26, subsets: 2, endpoint_bits: 8, delta_bits: [5, 5, 6], transformed: true, layout: &[
+        (R0, 0, 7), (B3, 1, 1), (B2, 4, 4), (G0, 0, 7), (B2, 5, 5), (G2, 4, 4), (B0, 0, 7), (B3, 5, 5),
+        (B3, 4, 4), (R1, 0, 4), (G3, 4, 4), (G2, 0, 3), (G1, 0, 4), (B3, 0, 0), (G3, 0, 3), (B1, 0, 5),
+        (B2, 0, 3), (R2, 0, 4), (B3, 2, 2), (R3, 0, 4), (B3, 3, 3)
+    ] },
+    Bc6hMode { value: 30, subsets: 2, endpoint_bits: 6, delta_bits: [6, 6, 6], transformed: false, layout: &[
+        (R0, 0, 5), (G3, 4, 4), (B3, 0, 0), (B3, 1, 1), (B2, 4, 4), (G0, 0, 5), (G2, 5, 5), (B2, 5, 5),
+        (B3, 2, 2), (G2, 4, 4), (B0, 0, 5), (G3, 5, 5), (B3, 3, 3), (B3, 5, 5), (B3, 4, 4), (R1, 0, 5),
+        (G2, 0, 3), (G1, 0, 5), (G3, 0, 3), (B1, 0, 5), (B2, 0, 3), (R2, 0, 5), (R3, 0, 5)
+    ] },
+    Bc6hMode { value: 3, subsets: 1, endpoint_bits: 10, delta_bits: [10, 10, 10], transformed: false, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 9), (G1, 0, 9), (B1, 0, 9)
+    ] },
+    Bc6hMode { value: 7, subsets: 1, endpoint_bits: 11, delta_bits: [9, 9, 9], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 8), (R0, 10, 10), (G1, 0, 8), (G0, 10, 10), (B1, 0, 8),
+        (B0, 10, 10)
+    ] },
+    Bc6hMode { value: 11, subsets: 1, endpoint_bits: 12, delta_bits: [8, 8, 8], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 7), (R0, 11, 10), (G1, 0, 7), (G0, 11, 10), (B1, 0, 7),
+        (B0, 11, 10)
+    ] },
+    Bc6hMode { value: 15, subsets: 1, endpoint_bits: 16, delta_bits: [4, 4, 4], transformed: true, layout: &[
+        (R0, 0, 9), (G0, 0, 9), (B0, 0, 9), (R1, 0, 3), (R0, 15, 10), (G1, 0, 3), (G0, 15, 10), (B1, 0, 3),
+        (B0, 15, 10)
+    ] }
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+/// Expands an endpoint to the 16-bit range interpolation works in.
+fn unquantize_bc6h(value: i32, bits: u32, signed: bool) -> i32 {
+    if signed {
+        if bits >= 16 {
+            return value;
+        }
+        let magnitude = value.abs();
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7fff
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        if value < 0 { -unquantized } else { unquantized }
+    } else if bits >= 15 {
+        value
+    } else if value == 0 {
+        0
+    } else if value == (1 << bits) - 1 {
+        0xffff
+    } else {
+        ((value << 16) + 0x8000) >> bits
+    }
+}
+
+/// Decodes a BC6H block into RGBA half floats, stored little endian. Alpha is always 1.
+fn decode_bc6h(block: &[u8], signed: bool) -> [[u8; 8]; 16] {
+    const ONE: [u8; 2] = [0x00, 0x3c];
+
+    let mut reader = BitReader {
+        bits: u128::from_le_bytes(block.try_into().unwrap()),
+        position: 0
+    };
+    let mut value = reader.read(2);
+    if value > 1 {
+        value |= reader.read(3) << 2;
+    }
+    let mode = match BC6H_MODES.iter().find(|mode| mode.value == value) {
+        Some(mode) => mode,
+        // Reserved modes, decoders have to output black.
+        None => return [[0, 0, 0, 0, 0, 0, ONE[0], ONE[1]]; 16]
+    };
+
+    let mut endpoints = [[0i32; 3]; 4];
+    for &(channel, first, last) in mode.layout {
+        for i in 0..first.abs_diff(last) + 1 {
+            let bit = if first <= last { first + i } else { first - i };
+            endpoints[channel / 3][channel % 3] |= (reader.read(1) << bit) as i32;
+        }
+    }
+    let partition = if mode.subsets == 2 { reader.read(5) as usize } else { 0 };
+
+    let endpoint_count = mode.subsets * 2;
+    let mask = (1 << mode.endpoint_bits) - 1;
+    for channel in 0..3 {
+        if signed {
+            endpoints[0][channel] = sign_extend(endpoints[0][channel], mode.endpoint_bits);
+        }
+        let base = endpoints[0][channel];
+        for endpoint in endpoints.iter_mut().take(endpoint_count).skip(1) {
+            if mode.transformed {
+                let delta = sign_extend(endpoint[channel], mode.delta_bits[channel]);
+                endpoint[channel] = (base + delta) & mask;
+            }
+            if signed {
+                endpoint[channel] = sign_extend(endpoint[channel], mode.endpoint_bits);
+            }
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for channel in endpoint.iter_mut() {
+            *channel = unquantize_bc6h(*channel, mode.endpoint_bits, signed);
+        }
+    }
+
+    let index_bits = if mode.subsets == 1 { 4 } else { 3 };
+    let mut pixels = [[0; 8]; 16];
+    for (pixel, color) in pixels.iter_mut().enumerate() {
+        let (subset, anchor) = if mode.subsets == 1 {
+            (0, pixel == 0)
+        } else {
+            ((PARTITIONS_2[partition] >> pixel) as usize & 1, pixel == 0 || pixel == ANCHORS_2[partition] as usize)
+        };
+        let index = reader.read(index_bits - anchor as u32) as usize;
+        let weight = if index_bits == 3 { WEIGHTS_3[index] } else { WEIGHTS_4[index] } as i32;
+        let (first, second) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        for channel in 0..3 {
+            let value = (first[channel] * (64 - weight) + second[channel] * weight + 32) >> 6;
+            // Scale the 16-bit range down to half float bits, 0x7bff being the largest finite one.
+            let half = if !signed {
+                (value * 31) >> 6
+            } else if value < 0 {
+                0x8000 | ((-value * 31) >> 5)
+            } else {
+                (value * 31) >> 5
+            };
+            color[channel * 2..channel * 2 + 2].copy_from_slice(&(half as u16).to_le_bytes());
+        }
+        color[6..].copy_from_slice(&ONE);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A BC1 block with the given endpoints and 2-bit indices, pixel 0 first.
+    fn bc1_block(color0: u16, color1: u16, indices: [u32; 16]) -> Vec<u8> {
+        let bits = indices.iter().enumerate().fold(0u32, |bits, (i, &index)| bits | index << (i * 2));
+        let mut block = color0.to_le_bytes().to_vec();
+        block.extend_from_slice(&color1.to_le_bytes());
+        block.extend_from_slice(&bits.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn bc1_endpoints_expand_and_interpolate() {
+        let mut indices = [0; 16];
+        indices[..4].copy_from_slice(&[0, 1, 2, 3]);
+        let pixels = decompress(BlockFormat::Bc1, 4, 4, &bc1_block(0xf800, 0x001f, indices));
+        assert_eq!(&pixels[..16], &[
+            255, 0, 0, 255,
+            0, 0, 255, 255,
+            170, 0, 85, 255,
+            85, 0, 170, 255
+        ]);
+
+        // A middle gray endpoint replicates its top bits into the low ones.
+        let pixels = decompress(BlockFormat::Bc1, 4, 4, &bc1_block(0x8410, 0x8410, [0; 16]));
+        assert_eq!(&pixels[..4], &[132, 130, 132, 255]);
+    }
+
+    #[test]
+    fn bc1_three_color_mode_has_transparent_black() {
+        let mut indices = [0; 16];
+        indices[..4].copy_from_slice(&[0, 1, 2, 3]);
+        let pixels = decompress(BlockFormat::Bc1, 4, 4, &bc1_block(0x001f, 0xf800, indices));
+        assert_eq!(&pixels[..16], &[
+            0, 0, 255, 255,
+            255, 0, 0, 255,
+            128, 0, 128, 255,
+            0, 0, 0, 0
+        ]);
+    }
+
+    #[test]
+    fn partial_edge_blocks_are_cropped() {
+        // 3x5 pixels need one column of two blocks, only the top row of the second is used.
+        let mut data = bc1_block(0xf800, 0xf800, [0; 16]);
+        let mut indices = [0; 16];
+        indices[3] = 1;
+        data.extend(bc1_block(0x07e0, 0x001f, indices));
+        assert_eq!(BlockFormat::Bc1.compressed_size(3, 5), data.len());
+
+        let pixels = decompress(BlockFormat::Bc1, 3, 5, &data);
+        assert_eq!(pixels.len(), 3 * 5 * 4);
+        for (i, pixel) in pixels.chunks(4).enumerate() {
+            let expected = if i < 12 { [255, 0, 0, 255] } else { [0, 255, 0, 255] };
+            assert_eq!(pixel, expected, "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn bc6h_unsigned_endpoints_span_the_half_float_range() {
+        // Mode 11 stores two 10-bit endpoints as is, the first at black, the second at the
+        // largest value. Pixel 0 uses index 0 and pixel 1 index 15.
+        let bits = 3u128 | 1023 << 35 | 1023 << 45 | 1023 << 55 | 15 << 68;
+        let pixels = decompress(BlockFormat::Bc6hUfloat, 4, 4, &bits.to_le_bytes());
+        assert_eq!(pixels.len(), 16 * 8);
+        let half = |pixel: usize, channel: usize| u16::from_le_bytes([pixels[pixel * 8 + channel * 2], pixels[pixel * 8 + channel * 2 + 1]]);
+        assert_eq!([half(0, 0), half(0, 1), half(0, 2), half(0, 3)], [0, 0, 0, 0x3c00]);
+        assert_eq!([half(1, 0), half(1, 1), half(1, 2), half(1, 3)], [0x7bff, 0x7bff, 0x7bff, 0x3c00]);
+    }
+
+    #[test]
+    fn bc6h_signed_high_bits_are_stored_reversed() {
+        // Mode 14 keeps the top 6 bits of its 16-bit endpoint after the deltas, highest bit
+        // first. Red is -15856 (0xc210) and green 15856 (0x3df0), which both scale to 1.0.
+        let red_low = 0x210u128;
+        let green_low = 0x1f0u128;
+        let red_high = 0b11u128 << 39;
+        let green_high = 0b1111u128 << 51;
+        let bits = 15 | red_low << 5 | green_low << 15 | red_high | green_high;
+        let pixels = decompress(BlockFormat::Bc6hSfloat, 4, 4, &bits.to_le_bytes());
+        for pixel in pixels.chunks(8) {
+            assert_eq!(pixel, [0x00, 0xbc, 0x00, 0x3c, 0x00, 0x00, 0x00, 0x3c]);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use crate::loader::LoadError;
+use crate::loader::bc::BlockFormat;
+use crate::loader::texture_image::{SourceFormat, TextureImage};
+use crate::renderer::texture::MAX_IMAGE_SIZE;
+
+/// Size of the magic number and `DDS_HEADER`.
+const HEADER_SIZE: usize = 128;
+
+/// Size of the `DDS_HEADER_DXT10` extension following the header of newer files.
+const DX10_HEADER_SIZE: usize = 20;
+
+const FLAG_MIPMAP_COUNT: u32 = 0x2_0000;
+const PIXEL_FLAG_ALPHA_PIXELS: u32 = 0x1;
+const PIXEL_FLAG_FOURCC: u32 = 0x4;
+const PIXEL_FLAG_RGB: u32 = 0x40;
+const PIXEL_FLAG_LUMINANCE: u32 = 0x2_0000;
+const CAPS2_CUBEMAP: u32 = 0x200;
+const CAPS2_CUBEMAP_ALL_FACES: u32 = 0xfc00;
+const CAPS2_VOLUME: u32 = 0x20_0000;
+const DX10_DIMENSION_TEXTURE2D: u32 = 3;
+const DX10_MISC_TEXTURECUBE: u32 = 0x4;
+
+fn error<T>(message: &str) -> Result<T, LoadError> {
+    Err(LoadError::Parse(format!("DDS: {}", message)))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+/// Maps a DXGI format to how its pixels are stored, for the formats wgpu can represent.
+fn dxgi_format(dxgi_format: u32) -> Option<SourceFormat> {
+    use wgpu::TextureFormat::*;
+    Some(SourceFormat::Uncompressed(match dxgi_format {
+        2 => Rgba32Float,
+        3 => Rgba32Uint,
+        4 => Rgba32Sint,
+        10 => Rgba16Float,
+        11 => Rgba16Unorm,
+        12 => Rgba16Uint,
+        13 => Rgba16Snorm,
+        14 => Rgba16Sint,
+        16 => Rg32Float,
+        17 => Rg32Uint,
+        18 => Rg32Sint,
+        24 => Rgb10a2Unorm,
+        26 => Rg11b10Float,
+        28 => Rgba8Unorm,
+        29 => Rgba8UnormSrgb,
+        30 => Rgba8Uint,
+        31 => Rgba8Snorm,
+        32 => Rgba8Sint,
+        34 => Rg16Float,
+        35 => Rg16Unorm,
+        36 => Rg16Uint,
+        37 => Rg16Snorm,
+        38 => Rg16Sint,
+        41 => R32Float,
+        42 => R32Uint,
+        43 => R32Sint,
+        49 => Rg8Unorm,
+        50 => Rg8Uint,
+        51 => Rg8Snorm,
+        52 => Rg8Sint,
+        54 => R16Float,
+        56 => R16Unorm,
+        57 => R16Uint,
+        58 => R16Snorm,
+        59 => R16Sint,
+        61 => R8Unorm,
+        62 => R8Uint,
+        63 => R8Snorm,
+        64 => R8Sint,
+        87 => Bgra8Unorm,
+        91 => Bgra8UnormSrgb,
+        _ => {
+            let (block_format, srgb) = match dxgi_format {
+                71 => (BlockFormat::Bc1, false),
+                72 => (BlockFormat::Bc1, true),
+                74 => (BlockFormat::Bc2, false),
+                75 => (BlockFormat::Bc2, true),
+                77 => (BlockFormat::Bc3, false),
+                78 => (BlockFormat::Bc3, true),
+                80 => (BlockFormat::Bc4, false),
+                83 => (BlockFormat::Bc5, false),
+                95 => (BlockFormat::Bc6hUfloat, false),
+                96 => (BlockFormat::Bc6hSfloat, false),
+                98 => (BlockFormat::Bc7, false),
+                99 => (BlockFormat::Bc7, true),
+                _ => return None
+            };
+            return Some(SourceFormat::Compressed(block_format, srgb));
+        }
+    }))
+}
+
+/// Maps the pixel format of files without the DX10 header. These don't say whether colors
+/// are sRGB encoded, so they're read as linear. Also returns whether alpha has to be filled
+/// in, for 32-bit formats with an unused fourth byte.
+fn legacy_format(bytes: &[u8]) -> Option<(SourceFormat, bool)> {
+    use wgpu::TextureFormat::*;
+    let flags = read_u32(bytes, 80);
+    let code = read_u32(bytes, 84);
+    let bit_count = read_u32(bytes, 88);
+    let masks = [read_u32(bytes, 92), read_u32(bytes, 96), read_u32(bytes, 100), read_u32(bytes, 104)];
+    let has_alpha = flags & PIXEL_FLAG_ALPHA_PIXELS != 0;
+
+    if flags & PIXEL_FLAG_FOURCC != 0 {
+        let block_format = if code == four_cc(b"DXT1") {
+            BlockFormat::Bc1
+        } else if code == four_cc(b"DXT3") {
+            BlockFormat::Bc2
+        } else if code == four_cc(b"DXT5") {
+            BlockFormat::Bc3
+        } else if code == four_cc(b"ATI1") || code == four_cc(b"BC4U") {
+            BlockFormat::Bc4
+        } else if code == four_cc(b"ATI2") || code == four_cc(b"BC5U") {
+            BlockFormat::Bc5
+        } else {
+            // Direct3D 9 float formats are stored as their D3DFORMAT number.
+            let format = match code {
+                111 => R16Float,
+                112 => Rg16Float,
+                113 => Rgba16Float,
+                114 => R32Float,
+                115 => Rg32Float,
+                116 => Rgba32Float,
+                _ => return None
+            };
+            return Some((SourceFormat::Uncompressed(format), false));
+        };
+        return Some((SourceFormat::Compressed(block_format, false), false));
+    }
+
+    if flags & PIXEL_FLAG_RGB != 0 && bit_count == 32 {
+        let format = match masks[..3] {
+            [0xff, 0xff00, 0xff_0000] => Rgba8Unorm,
+            [0xff_0000, 0xff00, 0xff] => Bgra8Unorm,
+            _ => return None
+        };
+        let alpha_used = has_alpha && masks[3] == 0xff00_0000;
+        return Some((SourceFormat::Uncompressed(format), !alpha_used));
+    }
+    if flags & PIXEL_FLAG_LUMINANCE != 0 && bit_count == 8 && !has_alpha {
+        return Some((SourceFormat::Uncompressed(R8Unorm), false));
+    }
+    None
+}
+
+/// Parses a DDS file with all of its mip levels, array layers and cube faces. Volume
+/// textures and cube maps missing faces aren't supported.
+pub fn parse_dds(bytes: &[u8]) -> Result<TextureImage, LoadError> {
+    if bytes.len() < HEADER_SIZE || bytes[..4] != *b"DDS " || read_u32(bytes, 4) != 124 {
+        return error("not a DDS file");
+    }
+    let flags = read_u32(bytes, 8);
+    let height = read_u32(bytes, 12).max(1);
+    let width = read_u32(bytes, 16);
+    let level_count = if flags & FLAG_MIPMAP_COUNT != 0 {
+        read_u32(bytes, 28).max(1)
+    } else {
+        1
+    };
+    let caps2 = read_u32(bytes, 112);
+    if width == 0 || level_count > 32 {
+        return error("invalid size");
+    }
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return error(&format!("images larger than {0}x{0} are not supported", MAX_IMAGE_SIZE));
+    }
+
+    let (format, fill_alpha, cube, layer_count, data_offset) = if read_u32(bytes, 84) == four_cc(b"DX10") {
+        if bytes.len() < HEADER_SIZE + DX10_HEADER_SIZE {
+            return error("unexpected end of file");
+        }
+        let format = match dxgi_format(read_u32(bytes, 128)) {
+            Some(format) => format,
+            None => return error(&format!("unsupported DXGI format {}", read_u32(bytes, 128)))
+        };
+        if read_u32(bytes, 132) != DX10_DIMENSION_TEXTURE2D {
+            return error("only 2D textures are supported");
+        }
+        let cube = read_u32(bytes, 136) & DX10_MISC_TEXTURECUBE != 0;
+        let array_size = read_u32(bytes, 140).max(1);
+        let layer_count = array_size as usize * if cube { 6 } else { 1 };
+        // Every layer stores at least a byte, which keeps corrupt counts from allocating.
+        if layer_count > bytes.len() {
+            return error("unexpected end of file");
+        }
+        (format, false, cube, layer_count as u32, HEADER_SIZE + DX10_HEADER_SIZE)
+    } else {
+        let code = read_u32(bytes, 84);
+        if read_u32(bytes, 80) & PIXEL_FLAG_FOURCC != 0 && (code == four_cc(b"DXT2") || code == four_cc(b"DXT4")) {
+            return error("DXT2 and DXT4 store premultiplied alpha, which isn't supported");
+        }
+        let (format, fill_alpha) = match legacy_format(bytes) {
+            Some(format) => format,
+            None => return error("unsupported pixel format")
+        };
+        if caps2 & CAPS2_VOLUME != 0 {
+            return error("volume textures are not supported");
+        }
+        let cube = caps2 & CAPS2_CUBEMAP != 0;
+        if cube && caps2 & CAPS2_CUBEMAP_ALL_FACES != CAPS2_CUBEMAP_ALL_FACES {
+            return error("cube maps need all six faces");
+        }
+        (format, fill_alpha, cube, if cube { 6 } else { 1 }, HEADER_SIZE)
+    };
+
+    // Every layer stores its whole mip chain before the next one starts.
+    let mut offset = data_offset;
+    let mut layers = Vec::with_capacity(layer_count as usize);
+    for _ in 0..layer_count {
+        let mut levels = Vec::with_capacity(level_count as usize);
+        for level in 0..level_count {
+            let level_width = (width >> level).max(1);
+            let level_height = (height >> level).max(1);
+            let size = format.image_size(level_width, level_height);
+            if offset + size > bytes.len() {
+                return error("unexpected end of file");
+            }
+            let mut data = format.decode(level_width, level_height, &bytes[offset..offset + size]);
+            if fill_alpha {
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel[3] = 255;
+                }
+            }
+            levels.push(data);
+            offset += size;
+        }
+        layers.push(levels);
+    }
+
+    Ok(TextureImage {
+        width,
+        height,
+        format: format.decoded_format(),
+        cube,
+        layers
+    })
+}
+
+pub fn load_dds(path: &Path) -> Result<TextureImage, LoadError> {
+    parse_dds(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x4 DXT1 file with a single level, or a DX10 one of the BC1 DXGI format.
+    fn bc1_file(dx10: bool) -> Vec<u8> {
+        let mut bytes = b"DDS ".to_vec();
+        for value in &[124u32, 0, 4, 4, 0, 0, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(76, 0);
+        let four_cc = if dx10 { b"DX10" } else { b"DXT1" };
+        bytes.extend_from_slice(&32u32.to_le_bytes());
+        bytes.extend_from_slice(&PIXEL_FLAG_FOURCC.to_le_bytes());
+        bytes.extend_from_slice(four_cc);
+        bytes.resize(HEADER_SIZE, 0);
+        if dx10 {
+            for value in &[71u32, DX10_DIMENSION_TEXTURE2D, 0, 1, 0] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&[0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn parses_complete_files() {
+        for &dx10 in &[false, true] {
+            let image = parse_dds(&bc1_file(dx10)).unwrap();
+            assert_eq!((image.width, image.height), (4, 4));
+            assert_eq!(image.format, wgpu::TextureFormat::Rgba8Unorm);
+            assert_eq!(&image.layers[0][0][..4], &[255, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        for &dx10 in &[false, true] {
+            let bytes = bc1_file(dx10);
+            for &length in &[0, 4, 84, HEADER_SIZE - 1, HEADER_SIZE, bytes.len() - 8, bytes.len() - 1] {
+                assert!(parse_dds(&bytes[..length]).is_err(), "{} bytes were accepted", length);
+            }
+        }
+    }
+
+    #[test]
+    fn premultiplied_alpha_is_rejected() {
+        for code in &[b"DXT2", b"DXT4"] {
+            let mut bytes = bc1_file(false);
+            bytes[84..88].copy_from_slice(*code);
+            bytes.extend_from_slice(&[0; 8]);
+            assert!(parse_dds(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn images_past_the_size_limit_are_rejected() {
+        let mut bytes = bc1_file(false);
+        bytes[16..20].copy_from_slice(&(MAX_IMAGE_SIZE + 4).to_le_bytes());
+        assert!(matches!(parse_dds(&bytes), Err(LoadError::Parse(message)) if message.contains("larger")));
+    }
+}
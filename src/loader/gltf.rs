@@ -0,0 +1,771 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::geometry::mesh_data::MeshData;
+use crate::geometry::vertex::Vertex;
+use crate::loader::LoadError;
+use crate::math::mat4::Mat4;
+use crate::renderer::context::Context;
+use crate::renderer::texture::{Texture, TextureOptions};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+/// A decoded image, converted to 8-bit RGBA.
+#[derive(Clone, Debug)]
+pub struct GltfImage {
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>
+}
+
+impl GltfImage {
+    /// Base color and emissive images are sRGB, the others should be uploaded with
+    /// `options.srgb` turned off.
+    pub fn to_texture(&self, options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        Texture::new_from_rgba_with_options(self.width, self.height, &self.data, options, context)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GltfAlphaMode {
+    Opaque,
+    Mask,
+    Blend
+}
+
+/// Metallic-roughness material parameters. Texture fields are indices into
+/// `GltfScene::images`.
+#[derive(Clone, Debug)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<usize>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<usize>,
+    pub alpha_mode: GltfAlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfPrimitive {
+    pub mesh: MeshData,
+    pub material: Option<usize>,
+    pub topology: wgpu::PrimitiveTopology
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+    pub transform: Mat4,
+    /// The node's transform combined with all of its parents'.
+    pub world_transform: Mat4
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub images: Vec<GltfImage>,
+    pub materials: Vec<GltfMaterial>,
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene.
+    pub roots: Vec<usize>
+}
+
+fn error<T>(message: &str) -> Result<T, LoadError> {
+    Err(LoadError::Parse(format!("glTF: {}", message)))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, LoadError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => error("unexpected end of binary data")
+    }
+}
+
+fn decode_base64(input: &str) -> Result<Vec<u8>, LoadError> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for c in input.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return error("invalid base64 data")
+        };
+        accumulator = (accumulator << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((accumulator >> bits) as u8);
+            accumulator &= (1 << bits) - 1;
+        }
+    }
+    Ok(output)
+}
+
+fn load_uri(uri: &str, base_dir: Option<&Path>) -> Result<Vec<u8>, LoadError> {
+    if uri.starts_with("data:") {
+        return match uri.find(";base64,") {
+            Some(start) => decode_base64(&uri[start + 8..]),
+            None => error("only base64 data URIs are supported")
+        };
+    }
+    if uri.contains("://") {
+        return error("only local files can be referenced");
+    }
+
+    let file = decode_percent(uri);
+    let path = match base_dir {
+        Some(dir) => dir.join(file),
+        None => Path::new(&file).to_path_buf()
+    };
+    Ok(std::fs::read(path)?)
+}
+
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            // The digits are read as bytes, a multi-byte character after the % isn't a
+            // char boundary to slice at.
+            let high = (bytes[i + 1] as char).to_digit(16);
+            let low = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(high), Some(low)) = (high, low) {
+                output.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn get_usize(value: &Value, key: &str) -> Option<usize> {
+    value.get(key).and_then(Value::as_u64).map(|v| v as usize)
+}
+
+fn get_f32(value: &Value, key: &str, default: f32) -> f32 {
+    value.get(key).and_then(Value::as_f64).map_or(default, |v| v as f32)
+}
+
+fn get_name(value: &Value) -> Option<String> {
+    value.get("name").and_then(Value::as_str).map(str::to_string)
+}
+
+fn get_floats(value: &Value, key: &str) -> Option<Vec<f32>> {
+    value.get(key).and_then(Value::as_array).map(|array| {
+        array.iter().map(|v| v.as_f64().unwrap_or(0.0) as f32).collect()
+    })
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> &'a [Value] {
+    json.get(key).and_then(Value::as_array).map_or(&[][..], Vec::as_slice)
+}
+
+fn component_size(component_type: u64) -> Result<usize, LoadError> {
+    match component_type {
+        5120 | 5121 => Ok(1),
+        5122 | 5123 => Ok(2),
+        5125 | 5126 => Ok(4),
+        _ => error("unknown accessor component type")
+    }
+}
+
+fn component_count(ty: &str) -> Result<usize, LoadError> {
+    match ty {
+        "SCALAR" => Ok(1),
+        "VEC2" => Ok(2),
+        "VEC3" => Ok(3),
+        "VEC4" | "MAT2" => Ok(4),
+        "MAT3" => Ok(9),
+        "MAT4" => Ok(16),
+        _ => error("unknown accessor type")
+    }
+}
+
+fn read_component(bytes: &[u8], offset: usize, component_type: u64) -> Result<f64, LoadError> {
+    let size = component_size(component_type)?;
+    let b = match bytes.get(offset..offset.saturating_add(size)) {
+        Some(b) => b,
+        None => return error("accessor reads past the end of its buffer view")
+    };
+    Ok(match component_type {
+        5120 => b[0] as i8 as f64,
+        5121 => b[0] as f64,
+        5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+        5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+        5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+    })
+}
+
+fn normalize(value: f64, component_type: u64) -> f64 {
+    match component_type {
+        5120 => (value / 127.0).max(-1.0),
+        5121 => value / 255.0,
+        5122 => (value / 32767.0).max(-1.0),
+        5123 => value / 65535.0,
+        _ => value
+    }
+}
+
+struct Document<'a> {
+    json: &'a Value,
+    buffers: Vec<Vec<u8>>
+}
+
+impl<'a> Document<'a> {
+    /// Returns the bytes of a buffer view and its stride, if it has one.
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), LoadError> {
+        let view = match array(self.json, "bufferViews").get(index) {
+            Some(view) => view,
+            None => return error("buffer view index out of range")
+        };
+        let buffer = match get_usize(view, "buffer").and_then(|b| self.buffers.get(b)) {
+            Some(buffer) => buffer,
+            None => return error("buffer index out of range")
+        };
+        let offset = get_usize(view, "byteOffset").unwrap_or(0);
+        let length = get_usize(view, "byteLength").unwrap_or(0);
+        match buffer.get(offset..offset + length) {
+            Some(bytes) => Ok((bytes, get_usize(view, "byteStride"))),
+            None => error("buffer view reaches past the end of its buffer")
+        }
+    }
+
+    /// Reads every component of an accessor, with sparse substitution applied and
+    /// without normalization. Returns the values and the component count per element.
+    fn read_accessor_raw(&self, index: usize) -> Result<(Vec<f64>, usize, u64), LoadError> {
+        let accessor = match array(self.json, "accessors").get(index) {
+            Some(accessor) => accessor,
+            None => return error("accessor index out of range")
+        };
+        let count = get_usize(accessor, "count").unwrap_or(0);
+        let component_type = accessor.get("componentType").and_then(Value::as_u64).unwrap_or(5126);
+        let components = component_count(accessor.get("type").and_then(Value::as_str).unwrap_or(""))?;
+        let size = component_size(component_type)?;
+        let length = match count.checked_mul(components) {
+            Some(length) => length,
+            None => return error("accessor count is too large")
+        };
+
+        let view = match get_usize(accessor, "bufferView") {
+            Some(view) => {
+                let (bytes, stride) = self.buffer_view(view)?;
+                let offset = get_usize(accessor, "byteOffset").unwrap_or(0);
+                let stride = stride.unwrap_or(size * components);
+                if stride < size * components {
+                    return error("buffer view stride is smaller than the accessor's elements");
+                }
+                // Check the last element fits before allocating room for all of them.
+                let end = count.checked_sub(1)
+                    .and_then(|last| last.checked_mul(stride))
+                    .and_then(|start| start.checked_add(offset))
+                    .and_then(|start| start.checked_add(size * components));
+                if count > 0 && end.is_none_or(|end| end > bytes.len()) {
+                    return error("accessor reads past the end of its buffer view");
+                }
+                Some((bytes, offset, stride))
+            },
+            None => None
+        };
+
+        let mut values = vec![0.0; length];
+        if let Some((bytes, offset, stride)) = view {
+            for element in 0..count {
+                for component in 0..components {
+                    values[element * components + component] = read_component(
+                        bytes,
+                        offset + element * stride + component * size,
+                        component_type
+                    )?;
+                }
+            }
+        }
+
+        if let Some(sparse) = accessor.get("sparse") {
+            let sparse_count = get_usize(sparse, "count").unwrap_or(0);
+            let (indices, values_info) = match (sparse.get("indices"), sparse.get("values")) {
+                (Some(indices), Some(values)) => (indices, values),
+                _ => return error("sparse accessor without indices or values")
+            };
+
+            let index_type = indices.get("componentType").and_then(Value::as_u64).unwrap_or(5125);
+            let index_size = component_size(index_type)?;
+            let (index_view, value_view) = match (get_usize(indices, "bufferView"), get_usize(values_info, "bufferView")) {
+                (Some(index_view), Some(value_view)) => (index_view, value_view),
+                _ => return error("sparse accessor without buffer views")
+            };
+            let (index_bytes, _) = self.buffer_view(index_view)?;
+            let index_offset = get_usize(indices, "byteOffset").unwrap_or(0);
+            let (value_bytes, _) = self.buffer_view(value_view)?;
+            let value_offset = get_usize(values_info, "byteOffset").unwrap_or(0);
+
+            for i in 0..sparse_count {
+                let element = read_component(index_bytes, index_offset + i * index_size, index_type)? as usize;
+                if element >= count {
+                    return error("sparse index out of range");
+                }
+                for component in 0..components {
+                    values[element * components + component] = read_component(
+                        value_bytes,
+                        value_offset + (i * components + component) * size,
+                        component_type
+                    )?;
+                }
+            }
+        }
+
+        Ok((values, components, component_type))
+    }
+
+    fn read_floats(&self, index: usize) -> Result<(Vec<f32>, usize), LoadError> {
+        let normalized = array(self.json, "accessors").get(index)
+            .and_then(|accessor| accessor.get("normalized"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let (values, components, component_type) = self.read_accessor_raw(index)?;
+        let values = values.into_iter()
+            .map(|value| if normalized { normalize(value, component_type) } else { value } as f32)
+            .collect();
+        Ok((values, components))
+    }
+
+    /// Reads a vertex attribute, checking that its accessor has one of the `types` and an
+    /// element for each of the `vertex_count` vertices.
+    fn read_attribute(&self, index: usize, types: &[&str], vertex_count: usize) -> Result<(Vec<f32>, usize), LoadError> {
+        if !types.contains(&self.accessor_type(index)?) {
+            return error("vertex attribute has the wrong accessor type");
+        }
+        let (values, components) = self.read_floats(index)?;
+        if values.len() != vertex_count * components {
+            return error("vertex attributes have different counts");
+        }
+        Ok((values, components))
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>, LoadError> {
+        if self.accessor_type(index)? != "SCALAR" {
+            return error("indices must be scalars");
+        }
+        let (values, _, component_type) = self.read_accessor_raw(index)?;
+        match component_type {
+            5121 | 5123 | 5125 => Ok(values.into_iter().map(|value| value as u32).collect()),
+            _ => error("indices must be unsigned integers")
+        }
+    }
+
+    fn accessor_type(&self, index: usize) -> Result<&str, LoadError> {
+        match array(self.json, "accessors").get(index) {
+            Some(accessor) => Ok(accessor.get("type").and_then(Value::as_str).unwrap_or("")),
+            None => error("accessor index out of range")
+        }
+    }
+}
+
+fn triangulate(mode: u64, indices: Vec<u32>) -> Result<(wgpu::PrimitiveTopology, Vec<u32>), LoadError> {
+    Ok(match mode {
+        0 => (wgpu::PrimitiveTopology::PointList, indices),
+        1 => (wgpu::PrimitiveTopology::LineList, indices),
+        2 => {
+            let mut indices = indices;
+            if let Some(&first) = indices.first() {
+                indices.push(first);
+            }
+            (wgpu::PrimitiveTopology::LineStrip, indices)
+        },
+        3 => (wgpu::PrimitiveTopology::LineStrip, indices),
+        4 => (wgpu::PrimitiveTopology::TriangleList, indices),
+        5 => (wgpu::PrimitiveTopology::TriangleStrip, indices),
+        6 => {
+            let mut list = Vec::with_capacity(indices.len().saturating_sub(2) * 3);
+            for i in 1..indices.len().saturating_sub(1) {
+                list.push(indices[0]);
+                list.push(indices[i]);
+                list.push(indices[i + 1]);
+            }
+            (wgpu::PrimitiveTopology::TriangleList, list)
+        },
+        _ => return error("unknown primitive mode")
+    })
+}
+
+fn load_primitive(document: &Document, primitive: &Value) -> Result<GltfPrimitive, LoadError> {
+    let attributes = match primitive.get("attributes") {
+        Some(attributes) => attributes,
+        None => return error("primitive without attributes")
+    };
+    let position = match get_usize(attributes, "POSITION") {
+        Some(position) => position,
+        None => return error("primitive without positions")
+    };
+
+    if document.accessor_type(position)? != "VEC3" {
+        return error("positions must be VEC3");
+    }
+    let (positions, _) = document.read_floats(position)?;
+    let mut vertices: Vec<Vertex> = positions.chunks_exact(3)
+        .map(|position| Vertex {
+            position: [position[0], position[1], position[2]],
+            ..Vertex::default()
+        })
+        .collect();
+    let vertex_count = vertices.len();
+
+    if let Some(normal) = get_usize(attributes, "NORMAL") {
+        let (normals, _) = document.read_attribute(normal, &["VEC3"], vertex_count)?;
+        for (vertex, normal) in vertices.iter_mut().zip(normals.chunks_exact(3)) {
+            vertex.normal = [normal[0], normal[1], normal[2]];
+        }
+    }
+    if let Some(tangent) = get_usize(attributes, "TANGENT") {
+        let (tangents, _) = document.read_attribute(tangent, &["VEC4"], vertex_count)?;
+        for (vertex, tangent) in vertices.iter_mut().zip(tangents.chunks_exact(4)) {
+            vertex.tangent = [tangent[0], tangent[1], tangent[2], tangent[3]];
+        }
+    }
+    if let Some(uv) = get_usize(attributes, "TEXCOORD_0") {
+        let (uvs, _) = document.read_attribute(uv, &["VEC2"], vertex_count)?;
+        for (vertex, uv) in vertices.iter_mut().zip(uvs.chunks_exact(2)) {
+            vertex.uv = [uv[0], uv[1]];
+        }
+    }
+    if let Some(color) = get_usize(attributes, "COLOR_0") {
+        let (colors, components) = document.read_attribute(color, &["VEC3", "VEC4"], vertex_count)?;
+        for (vertex, color) in vertices.iter_mut().zip(colors.chunks_exact(components)) {
+            let alpha = if components == 4 { color[3] } else { 1.0 };
+            vertex.color = [color[0], color[1], color[2], alpha];
+        }
+    }
+
+    let indices = match get_usize(primitive, "indices") {
+        Some(indices) => document.read_indices(indices)?,
+        None => (0..vertices.len() as u32).collect()
+    };
+    if indices.iter().any(|&index| index as usize >= vertices.len()) {
+        return error("vertex index out of range");
+    }
+
+    let mode = primitive.get("mode").and_then(Value::as_u64).unwrap_or(4);
+    let (topology, indices) = triangulate(mode, indices)?;
+
+    Ok(GltfPrimitive {
+        mesh: MeshData::new(vertices, indices),
+        material: get_usize(primitive, "material"),
+        topology
+    })
+}
+
+fn load_image(document: &Document, image: &Value, base_dir: Option<&Path>) -> Result<GltfImage, LoadError> {
+    let bytes = if let Some(uri) = image.get("uri").and_then(Value::as_str) {
+        load_uri(uri, base_dir)?
+    } else if let Some(view) = get_usize(image, "bufferView") {
+        document.buffer_view(view)?.0.to_vec()
+    } else {
+        return error("image without uri or buffer view");
+    };
+
+    let decoded = image::load_from_memory(&bytes)?.into_rgba8();
+    Ok(GltfImage {
+        name: get_name(image),
+        width: decoded.width(),
+        height: decoded.height(),
+        data: decoded.into_raw()
+    })
+}
+
+fn load_material(json: &Value, material: &Value) -> Result<GltfMaterial, LoadError> {
+    // Materials reference textures, which in turn reference the image we expose.
+    let texture = |value: Option<&Value>| -> Option<usize> {
+        let texture = get_usize(value?, "index")?;
+        get_usize(array(json, "textures").get(texture)?, "source")
+    };
+    let pbr = material.get("pbrMetallicRoughness");
+    let base_color = pbr.and_then(|pbr| get_floats(pbr, "baseColorFactor")).unwrap_or_else(|| vec![1.0; 4]);
+    let emissive = get_floats(material, "emissiveFactor").unwrap_or_else(|| vec![0.0; 3]);
+    if base_color.len() != 4 {
+        return error("baseColorFactor must have 4 components");
+    }
+    if emissive.len() != 3 {
+        return error("emissiveFactor must have 3 components");
+    }
+
+    Ok(GltfMaterial {
+        name: get_name(material),
+        base_color_factor: [base_color[0], base_color[1], base_color[2], base_color[3]],
+        base_color_texture: texture(pbr.and_then(|pbr| pbr.get("baseColorTexture"))),
+        metallic_factor: pbr.map_or(1.0, |pbr| get_f32(pbr, "metallicFactor", 1.0)),
+        roughness_factor: pbr.map_or(1.0, |pbr| get_f32(pbr, "roughnessFactor", 1.0)),
+        metallic_roughness_texture: texture(pbr.and_then(|pbr| pbr.get("metallicRoughnessTexture"))),
+        normal_texture: texture(material.get("normalTexture")),
+        normal_scale: material.get("normalTexture").map_or(1.0, |t| get_f32(t, "scale", 1.0)),
+        occlusion_texture: texture(material.get("occlusionTexture")),
+        occlusion_strength: material.get("occlusionTexture").map_or(1.0, |t| get_f32(t, "strength", 1.0)),
+        emissive_factor: [emissive[0], emissive[1], emissive[2]],
+        emissive_texture: texture(material.get("emissiveTexture")),
+        alpha_mode: match material.get("alphaMode").and_then(Value::as_str) {
+            Some("MASK") => GltfAlphaMode::Mask,
+            Some("BLEND") => GltfAlphaMode::Blend,
+            _ => GltfAlphaMode::Opaque
+        },
+        alpha_cutoff: get_f32(material, "alphaCutoff", 0.5),
+        double_sided: material.get("doubleSided").and_then(Value::as_bool).unwrap_or(false)
+    })
+}
+
+fn node_transform(node: &Value) -> Result<Mat4, LoadError> {
+    if let Some(matrix) = get_floats(node, "matrix") {
+        if matrix.len() != 16 {
+            return error("matrix must have 16 components");
+        }
+        let mut values = [0.0; 16];
+        values.copy_from_slice(&matrix);
+        return Ok(Mat4::from_column_major(values));
+    }
+    let t = get_floats(node, "translation").unwrap_or_else(|| vec![0.0; 3]);
+    let r = get_floats(node, "rotation").unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+    let s = get_floats(node, "scale").unwrap_or_else(|| vec![1.0; 3]);
+    if t.len() != 3 || r.len() != 4 || s.len() != 3 {
+        return error("translation, rotation or scale has the wrong number of components");
+    }
+    Ok(Mat4::translate(t[0], t[1], t[2]) * Mat4::from_quaternion(r[0], r[1], r[2], r[3]) * Mat4::scale(s[0], s[1], s[2]))
+}
+
+/// Parses a `.gltf` JSON document or a `.glb` binary. External buffers and images are
+/// read relative to `base_dir`; only local files and data URIs are supported.
+pub fn parse_gltf(bytes: &[u8], base_dir: Option<&Path>) -> Result<GltfScene, LoadError> {
+    let mut glb_bin = None;
+    let json_bytes = if bytes.len() >= 12 && read_u32(bytes, 0)? == GLB_MAGIC {
+        if read_u32(bytes, 4)? != 2 {
+            return error("only glTF 2.0 binaries are supported");
+        }
+        let mut json_chunk = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let length = read_u32(bytes, offset)? as usize;
+            let ty = read_u32(bytes, offset + 4)?;
+            let chunk = match bytes.get(offset + 8..offset + 8 + length) {
+                Some(chunk) => chunk,
+                None => return error("GLB chunk reaches past the end of the file")
+            };
+            match ty {
+                GLB_CHUNK_JSON => json_chunk = Some(chunk),
+                GLB_CHUNK_BIN => glb_bin = Some(chunk.to_vec()),
+                _ => {}
+            }
+            offset += 8 + length;
+        }
+        match json_chunk {
+            Some(chunk) => chunk,
+            None => return error("GLB without a JSON chunk")
+        }
+    } else {
+        bytes
+    };
+
+    let json: Value = match serde_json::from_slice(json_bytes) {
+        Ok(json) => json,
+        Err(e) => return Err(LoadError::Parse(format!("glTF: {}", e)))
+    };
+
+    let mut buffers = Vec::new();
+    for buffer in array(&json, "buffers") {
+        match buffer.get("uri").and_then(Value::as_str) {
+            Some(uri) => buffers.push(load_uri(uri, base_dir)?),
+            None => match glb_bin.take() {
+                Some(bin) => buffers.push(bin),
+                None => return error("buffer without uri outside of a GLB")
+            }
+        }
+    }
+    let document = Document {
+        json: &json,
+        buffers
+    };
+
+    let mut meshes = Vec::new();
+    for mesh in array(&json, "meshes") {
+        let mut primitives = Vec::new();
+        for primitive in array(mesh, "primitives") {
+            primitives.push(load_primitive(&document, primitive)?);
+        }
+        meshes.push(GltfMesh {
+            name: get_name(mesh),
+            primitives
+        });
+    }
+
+    let mut images = Vec::new();
+    for image in array(&json, "images") {
+        images.push(load_image(&document, image, base_dir)?);
+    }
+
+    let mut materials = Vec::new();
+    for material in array(&json, "materials") {
+        materials.push(load_material(&json, material)?);
+    }
+
+    let mut nodes = Vec::new();
+    for node in array(&json, "nodes") {
+        let transform = node_transform(node)?;
+        nodes.push(GltfNode {
+            name: get_name(node),
+            mesh: get_usize(node, "mesh"),
+            children: array(node, "children").iter()
+                .filter_map(|child| child.as_u64().map(|c| c as usize))
+                .collect(),
+            transform,
+            world_transform: transform
+        });
+    }
+
+    let scene = get_usize(&json, "scene").unwrap_or(0);
+    let roots: Vec<usize> = match array(&json, "scenes").get(scene) {
+        Some(scene) => array(scene, "nodes").iter()
+            .filter_map(|node| node.as_u64().map(|n| n as usize))
+            .collect(),
+        None => {
+            let mut is_child = vec![false; nodes.len()];
+            for node in &nodes {
+                for &child in &node.children {
+                    if child < is_child.len() {
+                        is_child[child] = true;
+                    }
+                }
+            }
+            (0..nodes.len()).filter(|&n| !is_child[n]).collect()
+        }
+    };
+
+    let mut stack: Vec<(usize, Mat4)> = roots.iter().map(|&root| (root, Mat4::identity())).collect();
+    let mut visited = vec![false; nodes.len()];
+    while let Some((index, parent)) = stack.pop() {
+        if index >= nodes.len() || visited[index] {
+            return error("node hierarchy is not a tree");
+        }
+        visited[index] = true;
+        let world = parent * nodes[index].transform;
+        nodes[index].world_transform = world;
+        for &child in &nodes[index].children {
+            stack.push((child, world));
+        }
+    }
+
+    Ok(GltfScene {
+        meshes,
+        images,
+        materials,
+        nodes,
+        roots
+    })
+}
+
+pub fn load_gltf(path: &Path) -> Result<GltfScene, LoadError> {
+    let bytes = std::fs::read(path)?;
+    parse_gltf(&bytes, path.parent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_escapes_are_decoded() {
+        assert_eq!(decode_percent("my%20model%2Fbuffer.bin"), "my model/buffer.bin");
+        assert_eq!(decode_percent("%e2%82%ac.bin"), "\u{20ac}.bin");
+    }
+
+    #[test]
+    fn malformed_percent_escapes_are_kept() {
+        assert_eq!(decode_percent("x%aé.bin"), "x%aé.bin");
+        assert_eq!(decode_percent("x%éa.bin"), "x%éa.bin");
+        assert_eq!(decode_percent("100%"), "100%");
+        assert_eq!(decode_percent("%zz"), "%zz");
+    }
+
+    #[test]
+    fn malformed_uri_is_an_error_not_a_panic() {
+        let json = r#"{"asset": {"version": "2.0"}, "buffers": [{"uri": "x%aé.bin", "byteLength": 4}]}"#;
+        assert!(parse_gltf(json.as_bytes(), Some(Path::new("/nonexistent"))).is_err());
+    }
+
+    /// A triangle with every attribute read from one zeroed 48 byte buffer.
+    fn triangle(accessors: &str, extra: &str) -> Result<GltfScene, LoadError> {
+        let json = format!(
+            r#"{{"asset": {{"version": "2.0"}},
+                "buffers": [{{"uri": "data:application/octet-stream;base64,{}", "byteLength": 48}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 48}}],
+                "accessors": [{}],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0, "NORMAL": 1}}}}]}}]{}}}"#,
+            "A".repeat(64),
+            accessors,
+            extra
+        );
+        parse_gltf(json.as_bytes(), None)
+    }
+
+    const POSITIONS: &str = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}"#;
+
+    #[test]
+    fn well_formed_triangle_is_loaded() {
+        let normals = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}"#;
+        let scene = triangle(&format!("{}, {}", POSITIONS, normals), "").unwrap();
+        assert_eq!(scene.meshes[0].primitives[0].mesh.vertices.len(), 3);
+    }
+
+    #[test]
+    fn attributes_with_the_wrong_type_are_rejected() {
+        let normals = r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC2"}"#;
+        assert!(triangle(&format!("{}, {}", POSITIONS, normals), "").is_err());
+        let normals = r#"{"bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3"}"#;
+        assert!(triangle(&format!("{}, {}", POSITIONS, normals), "").is_err());
+    }
+
+    #[test]
+    fn accessors_past_their_buffer_view_are_rejected() {
+        let normals = r#"{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"}"#;
+        assert!(triangle(&format!("{}, {}", POSITIONS, normals), "").is_err());
+        let normals = r#"{"bufferView": 0, "componentType": 5126, "count": 1000000000000, "type": "VEC3"}"#;
+        assert!(triangle(&format!("{}, {}", POSITIONS, normals), "").is_err());
+    }
+
+    #[test]
+    fn factors_and_transforms_with_the_wrong_length_are_rejected() {
+        let accessors = format!("{}, {}", POSITIONS, POSITIONS);
+        assert!(triangle(&accessors, r#", "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 1, 1]}}]"#).is_err());
+        assert!(triangle(&accessors, r#", "materials": [{"emissiveFactor": [1, 1]}]"#).is_err());
+        assert!(triangle(&accessors, r#", "nodes": [{"rotation": [0, 0, 1]}]"#).is_err());
+        assert!(triangle(&accessors, r#", "nodes": [{"translation": [1, 2]}]"#).is_err());
+        assert!(triangle(&accessors, r#", "nodes": [{"scale": [1, 2, 3, 4]}]"#).is_err());
+        assert!(triangle(&accessors, r#", "nodes": [{"scale": [1, 2, 3]}]"#).is_ok());
+    }
+}
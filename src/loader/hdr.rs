@@ -0,0 +1,158 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::loader::LoadError;
+use crate::renderer::context::Context;
+use crate::renderer::texture::{self, Texture, TextureError, TextureOptions};
+use crate::renderer::texture_format::f32_to_f16;
+
+/// A linear floating point RGBA image.
+#[derive(Clone, Debug)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>
+}
+
+impl HdrImage {
+    /// Uploads as `Rgba16Float`, which can be filtered everywhere and covers the range of
+    /// environment maps and lightmaps. `options.srgb` is ignored.
+    pub fn to_texture(&self, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        texture::check_image_size((self.width, self.height))?;
+        let data: Vec<u8> = self.data.iter().flat_map(|&value| f32_to_f16(value).to_le_bytes().to_vec()).collect();
+        Ok(Texture::new_with_format(self.width, self.height, wgpu::TextureFormat::Rgba16Float, &data, options, context)?)
+    }
+
+    /// Resamples the image as an equirectangular panorama into an `Rgba16Float` cube map, for
+    /// skyboxes and environment lighting.
+    pub fn to_cube_texture(&self, face_size: u32, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let data: Vec<u8> = self.data.iter().flat_map(|&value| f32_to_f16(value).to_le_bytes().to_vec()).collect();
+        Texture::new_cube_from_equirectangular(self.width, self.height, wgpu::TextureFormat::Rgba16Float, &data, face_size, options, context)
+    }
+
+    /// Uploads as `Rgba32Float` at full precision. Not every device can filter these.
+    pub fn to_texture_f32(&self, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        texture::check_image_size((self.width, self.height))?;
+        let data: Vec<u8> = self.data.iter().flat_map(|&value| value.to_le_bytes().to_vec()).collect();
+        Ok(Texture::new_with_format(self.width, self.height, wgpu::TextureFormat::Rgba32Float, &data, options, context)?)
+    }
+}
+
+/// Parses a Radiance RGBE (.hdr) file. Alpha is set to 1.
+pub fn parse_hdr(bytes: &[u8]) -> Result<HdrImage, LoadError> {
+    let decoder = image::hdr::HdrDecoder::new(Cursor::new(bytes))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+
+    let mut data = Vec::with_capacity(pixels.len() * 4);
+    for pixel in pixels {
+        data.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 1.0]);
+    }
+    Ok(HdrImage {
+        width: metadata.width,
+        height: metadata.height,
+        data
+    })
+}
+
+pub fn load_hdr(path: &Path) -> Result<HdrImage, LoadError> {
+    parse_hdr(&std::fs::read(path)?)
+}
+
+/// Parses the first layer with RGB channels of an OpenEXR file, at its largest resolution.
+/// Alpha defaults to 1 when the layer has none.
+pub fn parse_exr(bytes: &[u8]) -> Result<HdrImage, LoadError> {
+    use exr::prelude::*;
+
+    let image = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .rgba_channels(
+            |resolution, _| (resolution.width(), vec![0.0f32; resolution.width() * resolution.height() * 4]),
+            |(width, pixels): &mut (usize, Vec<f32>), position, (r, g, b, a): (f32, f32, f32, f32)| {
+                let offset = (position.y() * *width + position.x()) * 4;
+                pixels[offset..offset + 4].copy_from_slice(&[r, g, b, a]);
+            }
+        )
+        .first_valid_layer()
+        .all_attributes()
+        .from_buffered(Cursor::new(bytes))
+        .map_err(|error| LoadError::Parse(format!("EXR: {}", error)))?;
+
+    let size = image.layer_data.size;
+    Ok(HdrImage {
+        width: size.width() as u32,
+        height: size.height() as u32,
+        data: image.layer_data.channel_data.pixels.1
+    })
+}
+
+pub fn load_exr(path: &Path) -> Result<HdrImage, LoadError> {
+    parse_exr(&std::fs::read(path)?)
+}
+
+/// Loads a .hdr or .exr file, picked by the extension.
+pub fn load_hdr_image(path: &Path) -> Result<HdrImage, LoadError> {
+    match path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase).as_deref() {
+        Some("hdr") => load_hdr(path),
+        Some("exr") => load_exr(path),
+        _ => Err(LoadError::Parse(format!("{}: not an .hdr or .exr file", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 Radiance file holding (1, 0.5, 0.25) and (2, 0, 0). It's narrower than 8 pixels,
+    /// so the scanline is stored without run length encoding.
+    fn rgbe_file() -> Vec<u8> {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        bytes.extend_from_slice(&[128, 64, 32, 129, 128, 0, 0, 130]);
+        bytes
+    }
+
+    /// A 2x2 OpenEXR file without alpha, where red is x + 0.5 and green is y.
+    fn exr_file() -> Vec<u8> {
+        use exr::prelude::*;
+
+        let channels = SpecificChannels::rgb(|Vec2(x, y)| (x as f32 + 0.5, y as f32, 4.0f32));
+        let mut bytes = Vec::new();
+        Image::from_channels((2, 2), channels).write().to_buffered(Cursor::new(&mut bytes)).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn rgbe_pixels_are_decoded() {
+        let image = parse_hdr(&rgbe_file()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.data, vec![1.0, 0.5, 0.25, 1.0, 2.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn exr_pixels_are_decoded_with_opaque_alpha() {
+        let image = parse_exr(&exr_file()).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.data, vec![
+            0.5, 0.0, 4.0, 1.0, 1.5, 0.0, 4.0, 1.0,
+            0.5, 1.0, 4.0, 1.0, 1.5, 1.0, 4.0, 1.0
+        ]);
+    }
+
+    #[test]
+    fn files_are_picked_by_extension() {
+        let directory = std::env::temp_dir();
+        let hdr_path = directory.join(format!("hdr_test_{}.HDR", std::process::id()));
+        let exr_path = directory.join(format!("hdr_test_{}.exr", std::process::id()));
+        std::fs::write(&hdr_path, rgbe_file()).unwrap();
+        std::fs::write(&exr_path, exr_file()).unwrap();
+        let hdr = load_hdr_image(&hdr_path);
+        let exr = load_hdr_image(&exr_path);
+        std::fs::remove_file(&hdr_path).unwrap();
+        std::fs::remove_file(&exr_path).unwrap();
+
+        assert_eq!(hdr.unwrap().width, 2);
+        assert_eq!(exr.unwrap().height, 2);
+        assert!(matches!(load_hdr_image(Path::new("panorama.png")), Err(LoadError::Parse(_))));
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use crate::loader::LoadError;
+use crate::loader::bc::BlockFormat;
+use crate::loader::texture_image::{SourceFormat, TextureImage};
+use crate::renderer::texture::MAX_IMAGE_SIZE;
+
+const IDENTIFIER: [u8; 12] = [0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Size of the identifier, header and index before the level index.
+const LEVEL_INDEX_OFFSET: usize = 80;
+
+fn error<T>(message: &str) -> Result<T, LoadError> {
+    Err(LoadError::Parse(format!("KTX2: {}", message)))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    read_u32(bytes, offset) as u64 | (read_u32(bytes, offset + 4) as u64) << 32
+}
+
+/// Maps a Vulkan format to how its pixels are stored, for the formats wgpu can represent.
+fn source_format(vk_format: u32) -> Option<SourceFormat> {
+    use wgpu::TextureFormat::*;
+    Some(SourceFormat::Uncompressed(match vk_format {
+        9 => R8Unorm,
+        10 => R8Snorm,
+        13 => R8Uint,
+        14 => R8Sint,
+        16 => Rg8Unorm,
+        17 => Rg8Snorm,
+        20 => Rg8Uint,
+        21 => Rg8Sint,
+        37 => Rgba8Unorm,
+        38 => Rgba8Snorm,
+        41 => Rgba8Uint,
+        42 => Rgba8Sint,
+        43 => Rgba8UnormSrgb,
+        44 => Bgra8Unorm,
+        50 => Bgra8UnormSrgb,
+        64 => Rgb10a2Unorm,
+        70 => R16Unorm,
+        71 => R16Snorm,
+        74 => R16Uint,
+        75 => R16Sint,
+        76 => R16Float,
+        77 => Rg16Unorm,
+        78 => Rg16Snorm,
+        81 => Rg16Uint,
+        82 => Rg16Sint,
+        83 => Rg16Float,
+        91 => Rgba16Unorm,
+        92 => Rgba16Snorm,
+        95 => Rgba16Uint,
+        96 => Rgba16Sint,
+        97 => Rgba16Float,
+        98 => R32Uint,
+        99 => R32Sint,
+        100 => R32Float,
+        101 => Rg32Uint,
+        102 => Rg32Sint,
+        103 => Rg32Float,
+        107 => Rgba32Uint,
+        108 => Rgba32Sint,
+        109 => Rgba32Float,
+        122 => Rg11b10Float,
+        _ => {
+            let (block_format, srgb) = match vk_format {
+                131 | 133 => (BlockFormat::Bc1, false),
+                132 | 134 => (BlockFormat::Bc1, true),
+                135 => (BlockFormat::Bc2, false),
+                136 => (BlockFormat::Bc2, true),
+                137 => (BlockFormat::Bc3, false),
+                138 => (BlockFormat::Bc3, true),
+                139 => (BlockFormat::Bc4, false),
+                141 => (BlockFormat::Bc5, false),
+                143 => (BlockFormat::Bc6hUfloat, false),
+                144 => (BlockFormat::Bc6hSfloat, false),
+                145 => (BlockFormat::Bc7, false),
+                146 => (BlockFormat::Bc7, true),
+                _ => return None
+            };
+            return Some(SourceFormat::Compressed(block_format, srgb));
+        }
+    }))
+}
+
+/// Parses a KTX2 file with all of its mip levels, array layers and cube faces. Supercompressed
+/// files (Basis Universal, Zstandard, zlib) and 3D textures aren't supported.
+pub fn parse_ktx2(bytes: &[u8]) -> Result<TextureImage, LoadError> {
+    if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..12] != IDENTIFIER {
+        return error("not a KTX2 file");
+    }
+    let vk_format = read_u32(bytes, 12);
+    let width = read_u32(bytes, 20);
+    let height = read_u32(bytes, 24).max(1);
+    let depth = read_u32(bytes, 28);
+    let layer_count = read_u32(bytes, 32).max(1);
+    let face_count = read_u32(bytes, 36);
+    // Zero asks the loader to generate the mip chain, the file itself only has the base.
+    let level_count = read_u32(bytes, 40).max(1);
+    let supercompression = read_u32(bytes, 44);
+
+    let format = match source_format(vk_format) {
+        Some(format) => format,
+        None => return error(&format!("unsupported format {}", vk_format))
+    };
+    if supercompression != 0 {
+        return error("supercompressed files are not supported");
+    }
+    if depth > 1 {
+        return error("3D textures are not supported");
+    }
+    if face_count != 1 && face_count != 6 {
+        return error("face count has to be 1 or 6");
+    }
+    if width == 0 || level_count > 32 {
+        return error("invalid size");
+    }
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return error(&format!("images larger than {0}x{0} are not supported", MAX_IMAGE_SIZE));
+    }
+    let image_count = layer_count as usize * face_count as usize;
+    // Every image stores at least a byte, which keeps corrupt counts from allocating.
+    if bytes.len() < LEVEL_INDEX_OFFSET + level_count as usize * 24 || image_count > bytes.len() {
+        return error("unexpected end of file");
+    }
+
+    let mut layers = vec![Vec::with_capacity(level_count as usize); image_count];
+    for level in 0..level_count {
+        let entry = LEVEL_INDEX_OFFSET + level as usize * 24;
+        let offset = read_u64(bytes, entry) as usize;
+        let length = read_u64(bytes, entry + 8) as usize;
+        let level_width = (width >> level).max(1);
+        let level_height = (height >> level).max(1);
+        let image_size = format.image_size(level_width, level_height);
+        if length < image_size * image_count || offset > bytes.len() || length > bytes.len() - offset {
+            return error("level data is out of bounds");
+        }
+
+        // Images of a level are stored by layer, then by face.
+        for (image, levels) in layers.iter_mut().enumerate() {
+            let start = offset + image * image_size;
+            levels.push(format.decode(level_width, level_height, &bytes[start..start + image_size]));
+        }
+    }
+
+    Ok(TextureImage {
+        width,
+        height,
+        format: format.decoded_format(),
+        cube: face_count == 6,
+        layers
+    })
+}
+
+pub fn load_ktx2(path: &Path) -> Result<TextureImage, LoadError> {
+    parse_ktx2(&std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 RGBA8 file with a single level.
+    fn rgba8_file() -> Vec<u8> {
+        let mut bytes = IDENTIFIER.to_vec();
+        for value in &[37u32, 1, 2, 2, 0, 0, 1, 1, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(LEVEL_INDEX_OFFSET, 0);
+        for value in &[104u64, 16, 16] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend((0..16).map(|i| i as u8));
+        bytes
+    }
+
+    #[test]
+    fn parses_a_complete_file() {
+        let image = parse_ktx2(&rgba8_file()).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(image.layers, vec![vec![(0..16).collect::<Vec<u8>>()]]);
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let bytes = rgba8_file();
+        for &length in &[0, 11, 12, 48, LEVEL_INDEX_OFFSET - 1, LEVEL_INDEX_OFFSET, LEVEL_INDEX_OFFSET + 23, 104, bytes.len() - 1] {
+            assert!(parse_ktx2(&bytes[..length]).is_err(), "{} bytes were accepted", length);
+        }
+    }
+}
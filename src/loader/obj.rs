@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::geometry::mesh_data::MeshData;
+use crate::geometry::vertex::Vertex;
+use crate::loader::LoadError;
+
+#[derive(Clone, Debug)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub emissive: [f32; 3],
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub illumination: u32,
+    pub ambient_texture: Option<PathBuf>,
+    pub diffuse_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+    pub dissolve_texture: Option<PathBuf>
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.to_string(),
+            ambient: [0.0, 0.0, 0.0],
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.0, 0.0, 0.0],
+            emissive: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            dissolve: 1.0,
+            illumination: 0,
+            ambient_texture: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+            dissolve_texture: None
+        }
+    }
+}
+
+/// A run of indices in `ObjModel::mesh` that share a group name and material.
+#[derive(Clone, Debug)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<usize>,
+    pub first_index: usize,
+    pub index_count: usize
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjModel {
+    pub mesh: MeshData,
+    pub groups: Vec<ObjGroup>,
+    pub materials: Vec<ObjMaterial>
+}
+
+fn parse_floats(tokens: &[&str], line: usize) -> Result<Vec<f32>, LoadError> {
+    tokens.iter()
+        .map(|token| token.parse::<f32>().map_err(|_| {
+            LoadError::Parse(format!("line {}: invalid number '{}'", line, token))
+        }))
+        .collect()
+}
+
+fn parse_vec3(tokens: &[&str], line: usize) -> Result<[f32; 3], LoadError> {
+    let values = parse_floats(tokens, line)?;
+    if values.len() < 3 {
+        return Err(LoadError::Parse(format!("line {}: expected 3 components", line)));
+    }
+    Ok([values[0], values[1], values[2]])
+}
+
+fn parse_scalar(tokens: &[&str], line: usize) -> Result<f32, LoadError> {
+    match parse_floats(tokens, line)?.first() {
+        Some(&value) => Ok(value),
+        None => Err(LoadError::Parse(format!("line {}: expected a value", line)))
+    }
+}
+
+fn resolve_index(token: &str, count: usize, line: usize) -> Result<usize, LoadError> {
+    let index = token.parse::<i64>().map_err(|_| {
+        LoadError::Parse(format!("line {}: invalid index '{}'", line, token))
+    })?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(LoadError::Parse(format!("line {}: index {} out of range", line, index)));
+    }
+    Ok(resolved as usize)
+}
+
+fn resolve_path(base_dir: Option<&Path>, file: &str) -> PathBuf {
+    match base_dir {
+        Some(dir) => dir.join(file),
+        None => PathBuf::from(file)
+    }
+}
+
+/// Parses the contents of an MTL file, texture paths are resolved relative to `base_dir`.
+pub fn parse_mtl(source: &str, base_dir: Option<&Path>) -> Result<Vec<ObjMaterial>, LoadError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        if tokens[0] == "newmtl" {
+            let name = tokens[1..].join(" ");
+            materials.push(ObjMaterial::new(&name));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(LoadError::Parse(format!("line {}: statement before newmtl", line_number)))
+        };
+        // Texture statements may carry options before the file name, the name is always last.
+        let texture = || tokens.last().map(|file| resolve_path(base_dir, file));
+
+        match tokens[0] {
+            "Ka" => material.ambient = parse_vec3(&tokens[1..], line_number)?,
+            "Kd" => material.diffuse = parse_vec3(&tokens[1..], line_number)?,
+            "Ks" => material.specular = parse_vec3(&tokens[1..], line_number)?,
+            "Ke" => material.emissive = parse_vec3(&tokens[1..], line_number)?,
+            "Ns" => material.shininess = parse_scalar(&tokens[1..], line_number)?,
+            "d" => material.dissolve = parse_scalar(&tokens[1..], line_number)?,
+            "Tr" => material.dissolve = 1.0 - parse_scalar(&tokens[1..], line_number)?,
+            "illum" => material.illumination = parse_scalar(&tokens[1..], line_number)? as u32,
+            "map_Ka" => material.ambient_texture = texture(),
+            "map_Kd" => material.diffuse_texture = texture(),
+            "map_Ks" => material.specular_texture = texture(),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_texture = texture(),
+            "map_d" => material.dissolve_texture = texture(),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Parses the contents of an OBJ file into an indexed triangle list. Polygons are
+/// triangulated as fans, identical position/uv/normal triplets share a vertex.
+///
+/// Texture coordinates are flipped vertically to match the top-left origin textures are
+/// uploaded with. Normals are left zeroed when the file doesn't provide them. MTL files
+/// referenced with `mtllib` are read from `base_dir`.
+pub fn parse_obj(source: &str, base_dir: Option<&Path>) -> Result<ObjModel, LoadError> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<Option<[f32; 3]>> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut mesh = MeshData::default();
+    let mut lookup: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut materials = Vec::new();
+    let mut groups: Vec<ObjGroup> = Vec::new();
+
+    let mut group_name = String::from("default");
+    let mut material: Option<usize> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match tokens[0] {
+            "v" => {
+                let values = parse_floats(&tokens[1..], line_number)?;
+                if values.len() < 3 {
+                    return Err(LoadError::Parse(format!("line {}: expected 3 components", line_number)));
+                }
+                positions.push([values[0], values[1], values[2]]);
+                // Vertex colors are a common extension, written right after the position.
+                colors.push(if values.len() >= 6 {
+                    Some([values[3], values[4], values[5]])
+                } else {
+                    None
+                });
+            },
+            "vt" => {
+                let values = parse_floats(&tokens[1..], line_number)?;
+                if values.is_empty() {
+                    return Err(LoadError::Parse(format!("line {}: expected texture coordinates", line_number)));
+                }
+                let v = if values.len() > 1 { values[1] } else { 0.0 };
+                uvs.push([values[0], 1.0 - v]);
+            },
+            "vn" => normals.push(parse_vec3(&tokens[1..], line_number)?),
+            "f" => {
+                if tokens.len() < 4 {
+                    return Err(LoadError::Parse(format!("line {}: face with less than 3 vertices", line_number)));
+                }
+
+                let mut face = Vec::with_capacity(tokens.len() - 1);
+                for token in &tokens[1..] {
+                    let mut parts = token.split('/');
+                    let position = resolve_index(parts.next().unwrap_or(""), positions.len(), line_number)?;
+                    let uv = match parts.next() {
+                        Some(part) if !part.is_empty() => Some(resolve_index(part, uvs.len(), line_number)?),
+                        _ => None
+                    };
+                    let normal = match parts.next() {
+                        Some(part) if !part.is_empty() => Some(resolve_index(part, normals.len(), line_number)?),
+                        _ => None
+                    };
+
+                    let key = (position, uv, normal);
+                    let index = match lookup.get(&key) {
+                        Some(&index) => index,
+                        None => {
+                            let mut vertex = Vertex {
+                                position: positions[position],
+                                ..Vertex::default()
+                            };
+                            if let Some(color) = colors[position] {
+                                vertex.color = [color[0], color[1], color[2], 1.0];
+                            }
+                            if let Some(uv) = uv {
+                                vertex.uv = uvs[uv];
+                            }
+                            if let Some(normal) = normal {
+                                vertex.normal = normals[normal];
+                            }
+                            let index = mesh.vertices.len() as u32;
+                            mesh.vertices.push(vertex);
+                            lookup.insert(key, index);
+                            index
+                        }
+                    };
+                    face.push(index);
+                }
+
+                let needs_group = match groups.last() {
+                    Some(group) => group.name != group_name || group.material != material,
+                    None => true
+                };
+                if needs_group {
+                    groups.push(ObjGroup {
+                        name: group_name.clone(),
+                        material,
+                        first_index: mesh.indices.len(),
+                        index_count: 0
+                    });
+                }
+
+                for i in 1..face.len() - 1 {
+                    mesh.indices.push(face[0]);
+                    mesh.indices.push(face[i]);
+                    mesh.indices.push(face[i + 1]);
+                }
+                if let Some(group) = groups.last_mut() {
+                    group.index_count = mesh.indices.len() - group.first_index;
+                }
+            },
+            "g" | "o" => {
+                group_name = if tokens.len() > 1 {
+                    tokens[1..].join(" ")
+                } else {
+                    String::from("default")
+                };
+            },
+            "usemtl" => {
+                let name = tokens[1..].join(" ");
+                material = materials.iter().position(|m: &ObjMaterial| m.name == name);
+            },
+            "mtllib" => {
+                for file in &tokens[1..] {
+                    let path = resolve_path(base_dir, file);
+                    let source = std::fs::read_to_string(&path)?;
+                    let mtl_dir = path.parent().map(|dir| dir.to_path_buf());
+                    materials.extend(parse_mtl(&source, mtl_dir.as_deref())?);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(ObjModel {
+        mesh,
+        groups,
+        materials
+    })
+}
+
+pub fn load_obj(path: &Path) -> Result<ObjModel, LoadError> {
+    let source = std::fs::read_to_string(path)?;
+    parse_obj(&source, path.parent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\nf -4/-2/-1 -3/-2/-1 -2/-1/-1 -1/-1/-1\n";
+        let model = parse_obj(source, None).unwrap();
+        assert_eq!(model.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(model.mesh.vertices[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(model.mesh.vertices[3].position, [0.0, 1.0, 0.0]);
+        assert_eq!(model.mesh.vertices[0].uv, [0.0, 1.0]);
+        assert_eq!(model.mesh.vertices[2].uv, [1.0, 0.0]);
+        assert_eq!(model.mesh.vertices[2].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_face() {
+        let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nf -3 -2 -1\nv 0 1 0\nf -4 -2 -1\n";
+        let model = parse_obj(source, None).unwrap();
+        assert_eq!(model.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn out_of_range_negative_index_is_an_error() {
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nf -1 -2 -3\n", None).is_err());
+    }
+}
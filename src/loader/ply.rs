@@ -0,0 +1,409 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::geometry::mesh_data::MeshData;
+use crate::geometry::vertex::Vertex;
+use crate::loader::LoadError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ScalarType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<ScalarType, LoadError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::Uint8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::Uint16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::Uint32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return error(&format!("unknown property type '{}'", name))
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::Uint8 => 1,
+            ScalarType::Int16 | ScalarType::Uint16 => 2,
+            ScalarType::Int32 | ScalarType::Uint32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8
+        }
+    }
+
+    /// Scale that maps the type's range to 0..1, used for colors stored as integers.
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::Uint8 | ScalarType::Int8 => 255.0,
+            ScalarType::Uint16 | ScalarType::Int16 => 65535.0,
+            _ => 1.0
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    ty: ScalarType,
+    list_count: Option<ScalarType>
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+fn error<T>(message: &str) -> Result<T, LoadError> {
+    Err(LoadError::Parse(format!("PLY: {}", message)))
+}
+
+/// Reads scalars from the body of the file, in whichever encoding the header declared.
+struct BodyReader<'a> {
+    format: PlyFormat,
+    bytes: &'a [u8],
+    offset: usize,
+    tokens: std::str::SplitWhitespace<'a>
+}
+
+impl<'a> BodyReader<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, LoadError> {
+        if self.format == PlyFormat::Ascii {
+            return match self.tokens.next().map(str::parse::<f64>) {
+                Some(Ok(value)) => Ok(value),
+                Some(Err(_)) => error("invalid number in body"),
+                None => error("unexpected end of file")
+            };
+        }
+
+        let size = ty.size();
+        let b = match self.bytes.get(self.offset..self.offset + size) {
+            Some(b) => b,
+            None => return error("unexpected end of file")
+        };
+        self.offset += size;
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(b);
+        if self.format == PlyFormat::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match ty {
+            ScalarType::Int8 => buf[0] as i8 as f64,
+            ScalarType::Uint8 => buf[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Uint16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Uint32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buf)
+        })
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(PlyFormat, Vec<Element>, usize), LoadError> {
+    let end_marker = b"end_header";
+    let end = match bytes.windows(end_marker.len()).position(|window| window == end_marker) {
+        Some(end) => end,
+        None => return error("missing end_header")
+    };
+    let mut body_start = end + end_marker.len();
+    if bytes.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    if bytes.get(body_start) == Some(&b'\n') {
+        body_start += 1;
+    }
+
+    let header = String::from_utf8_lossy(&bytes[..end]);
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return error("missing ply magic");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", name, _] => format = Some(match *name {
+                "ascii" => PlyFormat::Ascii,
+                "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                _ => return error("unknown format")
+            }),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: match count.parse() {
+                    Ok(count) => count,
+                    Err(_) => return error("invalid element count")
+                },
+                properties: Vec::new()
+            }),
+            ["property", "list", count_ty, ty, name] => match elements.last_mut() {
+                Some(element) => element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: ScalarType::parse(ty)?,
+                    list_count: Some(ScalarType::parse(count_ty)?)
+                }),
+                None => return error("property before element")
+            },
+            ["property", ty, name] => match elements.last_mut() {
+                Some(element) => element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: ScalarType::parse(ty)?,
+                    list_count: None
+                }),
+                None => return error("property before element")
+            },
+            _ => {}
+        }
+    }
+
+    match format {
+        Some(format) => Ok((format, elements, body_start)),
+        None => error("missing format")
+    }
+}
+
+/// Parses an ASCII or binary PLY file. Faces are triangulated as fans; integer colors
+/// are normalized to 0..1.
+pub fn parse_ply(bytes: &[u8]) -> Result<MeshData, LoadError> {
+    let (format, elements, body_start) = parse_header(bytes)?;
+    let body = &bytes[body_start..];
+    let text = if format == PlyFormat::Ascii {
+        match std::str::from_utf8(body) {
+            Ok(text) => text,
+            Err(_) => return error("ASCII body is not valid UTF-8")
+        }
+    } else {
+        ""
+    };
+    let mut reader = BodyReader {
+        format,
+        bytes: body,
+        offset: 0,
+        tokens: text.split_whitespace()
+    };
+
+    let mut mesh = MeshData::default();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut vertex = Vertex::default();
+            let mut face = Vec::new();
+
+            for property in &element.properties {
+                if let Some(count_ty) = property.list_count {
+                    let count = reader.read(count_ty)? as usize;
+                    for _ in 0..count {
+                        let value = reader.read(property.ty)?;
+                        if element.name == "face" && (property.name == "vertex_indices" || property.name == "vertex_index") {
+                            face.push(value as u32);
+                        }
+                    }
+                    continue;
+                }
+
+                let value = reader.read(property.ty)?;
+                if element.name != "vertex" {
+                    continue;
+                }
+                let color = (value / property.ty.color_scale()) as f32;
+                let value = value as f32;
+                match property.name.as_str() {
+                    "x" => vertex.position[0] = value,
+                    "y" => vertex.position[1] = value,
+                    "z" => vertex.position[2] = value,
+                    "nx" => vertex.normal[0] = value,
+                    "ny" => vertex.normal[1] = value,
+                    "nz" => vertex.normal[2] = value,
+                    "u" | "s" | "texture_u" => vertex.uv[0] = value,
+                    // PLY puts the texture origin at the bottom left.
+                    "v" | "t" | "texture_v" => vertex.uv[1] = 1.0 - value,
+                    "red" | "r" => vertex.color[0] = color,
+                    "green" | "g" => vertex.color[1] = color,
+                    "blue" | "b" => vertex.color[2] = color,
+                    "alpha" | "a" => vertex.color[3] = color,
+                    _ => {}
+                }
+            }
+
+            if element.name == "vertex" {
+                mesh.vertices.push(vertex);
+            }
+            for i in 1..face.len().saturating_sub(1) {
+                mesh.indices.push(face[0]);
+                mesh.indices.push(face[i]);
+                mesh.indices.push(face[i + 1]);
+            }
+        }
+    }
+
+    if mesh.indices.iter().any(|&index| index as usize >= mesh.vertices.len()) {
+        return error("face index out of range");
+    }
+    Ok(mesh)
+}
+
+pub fn load_ply(path: &Path) -> Result<MeshData, LoadError> {
+    parse_ply(&std::fs::read(path)?)
+}
+
+/// Writes positions, normals, texture coordinates and 8-bit colors of every vertex, and
+/// the index list as triangles.
+pub fn write_ply<W: Write>(mesh: &MeshData, format: PlyFormat, writer: &mut W) -> std::io::Result<()> {
+    let format_name = match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        PlyFormat::BinaryBigEndian => "binary_big_endian"
+    };
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format {} 1.0", format_name)?;
+    writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+    for name in &["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(writer, "property float {}", name)?;
+    }
+    for name in &["red", "green", "blue", "alpha"] {
+        writeln!(writer, "property uchar {}", name)?;
+    }
+    writeln!(writer, "element face {}", mesh.indices.len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    for vertex in &mesh.vertices {
+        let floats = [
+            vertex.position[0], vertex.position[1], vertex.position[2],
+            vertex.normal[0], vertex.normal[1], vertex.normal[2],
+            vertex.uv[0], 1.0 - vertex.uv[1]
+        ];
+        let colors = [
+            to_byte(vertex.color[0]), to_byte(vertex.color[1]),
+            to_byte(vertex.color[2]), to_byte(vertex.color[3])
+        ];
+        match format {
+            PlyFormat::Ascii => {
+                let floats: Vec<String> = floats.iter().map(f32::to_string).collect();
+                writeln!(writer, "{} {} {} {} {}", floats.join(" "), colors[0], colors[1], colors[2], colors[3])?;
+            },
+            PlyFormat::BinaryLittleEndian => {
+                for value in &floats {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+                writer.write_all(&colors)?;
+            },
+            PlyFormat::BinaryBigEndian => {
+                for value in &floats {
+                    writer.write_all(&value.to_be_bytes())?;
+                }
+                writer.write_all(&colors)?;
+            }
+        }
+    }
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?,
+            PlyFormat::BinaryLittleEndian => {
+                writer.write_all(&[3])?;
+                for index in triangle {
+                    writer.write_all(&index.to_le_bytes())?;
+                }
+            },
+            PlyFormat::BinaryBigEndian => {
+                writer.write_all(&[3])?;
+                for index in triangle {
+                    writer.write_all(&index.to_be_bytes())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn save_ply(path: &Path, mesh: &MeshData, format: PlyFormat) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_ply(mesh, format, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> MeshData {
+        let vertex = |position: [f32; 3], uv: [f32; 2], color: [f32; 4]| Vertex {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            uv,
+            color,
+            ..Vertex::default()
+        };
+        MeshData {
+            vertices: vec![
+                vertex([-0.5, -0.5, 0.0], [0.0, 1.0], [1.0, 0.0, 0.0, 1.0]),
+                vertex([0.5, -0.5, 0.0], [1.0, 1.0], [0.0, 1.0, 0.0, 1.0]),
+                vertex([0.5, 0.5, 0.25], [1.0, 0.0], [0.0, 0.0, 1.0, 0.2]),
+                vertex([-0.5, 0.5, 1e-3], [0.0, 0.0], [1.0, 1.0, 1.0, 0.0])
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3]
+        }
+    }
+
+    fn round_trip(format: PlyFormat) {
+        let mesh = quad();
+        let mut bytes = Vec::new();
+        write_ply(&mesh, format, &mut bytes).unwrap();
+        let parsed = parse_ply(&bytes).unwrap();
+
+        assert_eq!(parsed.indices, mesh.indices);
+        assert_eq!(parsed.vertices.len(), mesh.vertices.len());
+        for (parsed, vertex) in parsed.vertices.iter().zip(&mesh.vertices) {
+            assert_eq!(parsed.position, vertex.position);
+            assert_eq!(parsed.normal, vertex.normal);
+            assert_eq!(parsed.uv, vertex.uv);
+            for (a, b) in parsed.color.iter().zip(&vertex.color) {
+                assert!((a - b).abs() <= 0.5 / 255.0);
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        round_trip(PlyFormat::Ascii);
+    }
+
+    #[test]
+    fn binary_little_endian_round_trip() {
+        round_trip(PlyFormat::BinaryLittleEndian);
+    }
+
+    #[test]
+    fn binary_big_endian_round_trip() {
+        round_trip(PlyFormat::BinaryBigEndian);
+    }
+
+    #[test]
+    fn texture_coordinates_are_flipped_to_a_top_left_origin() {
+        let source = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nproperty float s\nproperty float t\nend_header\n0 0 0 0.25 0.75\n";
+        let mesh = parse_ply(source.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices[0].uv, [0.25, 0.25]);
+    }
+}
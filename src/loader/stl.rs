@@ -0,0 +1,228 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::geometry::mesh_data::MeshData;
+use crate::geometry::vector::{cross, normalize, sub};
+use crate::geometry::vertex::Vertex;
+use crate::loader::LoadError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    Ascii,
+    Binary
+}
+
+fn error<T>(message: &str) -> Result<T, LoadError> {
+    Err(LoadError::Parse(format!("STL: {}", message)))
+}
+
+fn push_facet(mesh: &mut MeshData, normal: [f32; 3], corners: [[f32; 3]; 3]) {
+    // Facet normals are often left zeroed by exporters, fall back to the winding.
+    let normal = if normal == [0.0, 0.0, 0.0] {
+        normalize(cross(sub(corners[1], corners[0]), sub(corners[2], corners[0])))
+    } else {
+        normal
+    };
+    for &position in &corners {
+        mesh.indices.push(mesh.vertices.len() as u32);
+        mesh.vertices.push(Vertex {
+            position,
+            normal,
+            ..Vertex::default()
+        });
+    }
+}
+
+fn read_vec3(bytes: &[u8]) -> [f32; 3] {
+    let f = |i: usize| f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    [f(0), f(4), f(8)]
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<MeshData, LoadError> {
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let mut mesh = MeshData::default();
+    for facet in bytes[84..].chunks_exact(50).take(count) {
+        push_facet(&mut mesh, read_vec3(&facet[0..12]), [
+            read_vec3(&facet[12..24]),
+            read_vec3(&facet[24..36]),
+            read_vec3(&facet[36..48])
+        ]);
+    }
+    Ok(mesh)
+}
+
+fn parse_ascii(text: &str) -> Result<MeshData, LoadError> {
+    let mut mesh = MeshData::default();
+    let mut normal = [0.0; 3];
+    let mut corners = Vec::with_capacity(3);
+
+    let parse = |tokens: &[&str]| -> Result<[f32; 3], LoadError> {
+        if tokens.len() < 3 {
+            return error("expected 3 components");
+        }
+        let mut result = [0.0; 3];
+        for (value, token) in result.iter_mut().zip(tokens) {
+            *value = match token.parse() {
+                Ok(value) => value,
+                Err(_) => return error(&format!("invalid number '{}'", token))
+            };
+        }
+        Ok(result)
+    };
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["facet", "normal", rest @ ..] => {
+                normal = parse(rest)?;
+                corners.clear();
+            },
+            ["vertex", rest @ ..] => corners.push(parse(rest)?),
+            ["endfacet"] => {
+                if corners.len() != 3 {
+                    return error("facet without exactly 3 vertices");
+                }
+                push_facet(&mut mesh, normal, [corners[0], corners[1], corners[2]]);
+            },
+            _ => {}
+        }
+    }
+    Ok(mesh)
+}
+
+/// Parses an ASCII or binary STL file. Facets don't share vertices, every vertex carries
+/// its facet's normal.
+pub fn parse_stl(bytes: &[u8]) -> Result<MeshData, LoadError> {
+    // Binary files may also start with "solid", so trust the size their header implies.
+    let binary_size = if bytes.len() >= 84 {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        count.checked_mul(50).and_then(|size| size.checked_add(84))
+    } else {
+        None
+    };
+    if binary_size == Some(bytes.len()) {
+        return parse_binary(bytes);
+    }
+    if bytes.starts_with(b"solid") {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return parse_ascii(text);
+        }
+    }
+    // Some exporters pad binary files past the last facet.
+    match binary_size {
+        Some(size) if bytes.len() >= size => parse_binary(bytes),
+        _ => error("file is neither ASCII nor a complete binary STL")
+    }
+}
+
+pub fn load_stl(path: &Path) -> Result<MeshData, LoadError> {
+    parse_stl(&std::fs::read(path)?)
+}
+
+/// Writes every triangle of the mesh as a facet, with normals computed from the winding.
+pub fn write_stl<W: Write>(mesh: &MeshData, format: StlFormat, writer: &mut W) -> std::io::Result<()> {
+    let triangles = mesh.indices.chunks_exact(3).map(|triangle| {
+        let a = mesh.vertices[triangle[0] as usize].position;
+        let b = mesh.vertices[triangle[1] as usize].position;
+        let c = mesh.vertices[triangle[2] as usize].position;
+        (normalize(cross(sub(b, a), sub(c, a))), [a, b, c])
+    });
+
+    match format {
+        StlFormat::Ascii => {
+            writeln!(writer, "solid mesh")?;
+            for (normal, corners) in triangles {
+                writeln!(writer, "facet normal {} {} {}", normal[0], normal[1], normal[2])?;
+                writeln!(writer, "outer loop")?;
+                for corner in &corners {
+                    writeln!(writer, "vertex {} {} {}", corner[0], corner[1], corner[2])?;
+                }
+                writeln!(writer, "endloop")?;
+                writeln!(writer, "endfacet")?;
+            }
+            writeln!(writer, "endsolid mesh")?;
+        },
+        StlFormat::Binary => {
+            writer.write_all(&[0u8; 80])?;
+            writer.write_all(&((mesh.indices.len() / 3) as u32).to_le_bytes())?;
+            for (normal, corners) in triangles {
+                for value in normal.iter().chain(corners.iter().flatten()) {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+                writer.write_all(&[0, 0])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn save_stl(path: &Path, mesh: &MeshData, format: StlFormat) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_stl(mesh, format, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tetrahedron() -> MeshData {
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        MeshData {
+            vertices: positions.iter().map(|&position| Vertex {
+                position,
+                ..Vertex::default()
+            }).collect(),
+            indices: vec![0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3]
+        }
+    }
+
+    fn round_trip(format: StlFormat) {
+        let mesh = tetrahedron();
+        let mut bytes = Vec::new();
+        write_stl(&mesh, format, &mut bytes).unwrap();
+        let parsed = parse_stl(&bytes).unwrap();
+
+        assert_eq!(parsed.indices, (0..12).collect::<Vec<u32>>());
+        for (triangle, corners) in mesh.indices.chunks(3).zip(parsed.vertices.chunks(3)) {
+            let positions: Vec<[f32; 3]> = triangle.iter().map(|&index| mesh.vertices[index as usize].position).collect();
+            let normal = normalize(cross(sub(positions[1], positions[0]), sub(positions[2], positions[0])));
+            for (position, corner) in positions.iter().zip(corners) {
+                assert_eq!(corner.position, *position);
+                assert_eq!(corner.normal, normal);
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        round_trip(StlFormat::Ascii);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        round_trip(StlFormat::Binary);
+    }
+
+    #[test]
+    fn zeroed_facet_normals_are_computed_from_the_winding() {
+        let source = "solid t\nfacet normal 0 0 0\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        let mesh = parse_stl(source.as_bytes()).unwrap();
+        assert!(mesh.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn binary_files_with_trailing_bytes_are_accepted() {
+        let mut bytes = Vec::new();
+        write_stl(&tetrahedron(), StlFormat::Binary, &mut bytes).unwrap();
+        bytes.extend_from_slice(&[0xff; 7]);
+        assert_eq!(parse_stl(&bytes).unwrap().indices.len(), 12);
+    }
+
+    #[test]
+    fn oversized_facet_counts_are_rejected() {
+        let mut bytes = vec![0u8; 84];
+        bytes[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_stl(&bytes).is_err());
+    }
+}
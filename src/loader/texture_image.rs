@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use crate::loader::LoadError;
+use crate::loader::bc::{self, BlockFormat};
+use crate::loader::dds::load_dds;
+use crate::loader::ktx2::load_ktx2;
+use crate::renderer::context::Context;
+use crate::renderer::texture::{Texture, TextureError};
+use crate::renderer::texture_format;
+
+/// A texture file's contents with its own mip levels, array layers or cube faces, in a
+/// format that can be uploaded as is. Block compressed files are decompressed when they're
+/// parsed, see `loader::bc`. They can't be uploaded compressed even on devices that support
+/// it, since wgpu 0.4 has no BC texture formats.
+#[derive(Clone, Debug)]
+pub struct TextureImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    /// Whether the layers are cube faces, six per cube in +X, -X, +Y, -Y, +Z, -Z order.
+    pub cube: bool,
+    /// Pixels as `layers[layer][level]`, each level tightly packed and half the size of the
+    /// one before it.
+    pub layers: Vec<Vec<Vec<u8>>>
+}
+
+impl TextureImage {
+    pub fn get_mip_level_count(&self) -> u32 {
+        self.layers.first().map_or(0, |levels| levels.len() as u32)
+    }
+
+    pub fn get_view_dimension(&self) -> wgpu::TextureViewDimension {
+        match (self.cube, self.layers.len()) {
+            (true, 6) => wgpu::TextureViewDimension::Cube,
+            (true, _) => wgpu::TextureViewDimension::CubeArray,
+            (false, 1) => wgpu::TextureViewDimension::D2,
+            (false, _) => wgpu::TextureViewDimension::D2Array
+        }
+    }
+
+    /// Uploads every layer and mip level stored in the file.
+    pub fn to_texture(&self, context: &mut Context) -> Result<Texture, TextureError> {
+        Texture::new_from_layers(self.width, self.height, self.format, &self.layers, self.get_view_dimension(), context)
+    }
+}
+
+/// Loads a .ktx2 or .dds file, picked by the extension.
+pub fn load_texture_image(path: &Path) -> Result<TextureImage, LoadError> {
+    match path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase).as_deref() {
+        Some("ktx2") => load_ktx2(path),
+        Some("dds") => load_dds(path),
+        _ => Err(LoadError::Parse(format!("{}: not a .ktx2 or .dds file", path.display())))
+    }
+}
+
+/// How a container stores its pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SourceFormat {
+    Uncompressed(wgpu::TextureFormat),
+    Compressed(BlockFormat, bool)
+}
+
+impl SourceFormat {
+    pub(crate) fn decoded_format(self) -> wgpu::TextureFormat {
+        match self {
+            SourceFormat::Uncompressed(format) => format,
+            SourceFormat::Compressed(block_format, srgb) => block_format.decoded_format(srgb)
+        }
+    }
+
+    /// The size in bytes of one stored image of the given size.
+    pub(crate) fn image_size(self, width: u32, height: u32) -> usize {
+        match self {
+            SourceFormat::Uncompressed(format) => {
+                let bytes_per_pixel = texture_format::bytes_per_pixel(format).unwrap_or(0);
+                width as usize * height as usize * bytes_per_pixel as usize
+            },
+            SourceFormat::Compressed(block_format, _) => block_format.compressed_size(width, height)
+        }
+    }
+
+    pub(crate) fn decode(self, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        match self {
+            SourceFormat::Uncompressed(_) => data.to_vec(),
+            SourceFormat::Compressed(block_format, _) => bc::decompress(block_format, width, height, data)
+        }
+    }
+}
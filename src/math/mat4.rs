@@ -1,10 +1,13 @@
+use std::ops::Mul;
+
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Mat4 {
     values: [f32; 16]
 }
 
 impl Mat4 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(m00: f32, m01: f32, m02: f32, m03: f32,
                m10: f32, m11: f32, m12: f32, m13: f32,
                m20: f32, m21: f32, m22: f32, m23: f32,
@@ -37,6 +40,40 @@ impl Mat4 {
         )
     }
 
+    pub fn scale(x: f32, y: f32, z: f32) -> Mat4 {
+        Mat4::new(
+            x, 0.0, 0.0, 0.0,
+            0.0, y, 0.0, 0.0,
+            0.0, 0.0, z, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        )
+    }
+
+    /// Rotation matrix from a unit quaternion.
+    pub fn from_quaternion(x: f32, y: f32, z: f32, w: f32) -> Mat4 {
+        Mat4::new(
+            1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0,
+            2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0,
+            2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        )
+    }
+
+    /// Builds a matrix from 16 values stored column by column, as glTF and OpenGL do.
+    pub fn from_column_major(values: [f32; 16]) -> Mat4 {
+        let mut result = Mat4 { values: [0.0; 16] };
+        for row in 0..4 {
+            for col in 0..4 {
+                result.values[row * 4 + col] = values[col * 4 + row];
+            }
+        }
+        result
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.values[row * 4 + col]
+    }
+
     pub fn rotate(x: f32, y: f32, z: f32) -> Mat4 {
         let sx = x.sin();
         let sy = y.sin();
@@ -62,4 +99,20 @@ impl Mat4 {
             0.0, 0.0, -1.0, 0.0
         )
     }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut result = Mat4 { values: [0.0; 16] };
+        for row in 0..4 {
+            for col in 0..4 {
+                result.values[row * 4 + col] = (0..4)
+                    .map(|i| self.values[row * 4 + i] * rhs.values[i * 4 + col])
+                    .sum();
+            }
+        }
+        result
+    }
 }
\ No newline at end of file
@@ -1,47 +1,71 @@
+pub mod blend_mode;
+pub mod context;
+pub mod context_builder;
+pub mod drawable;
+pub mod dynamic_mesh;
+pub mod frame;
+pub mod index;
 pub mod mesh;
+pub mod mesh_pool;
 pub mod renderer_builder;
+pub mod sampler;
 pub mod shader;
+pub mod skybox;
 pub mod texture;
+pub mod texture_format;
 pub mod uniform_buffer;
+mod window_surface;
 
 use self::uniform_buffer::UniformBuffer;
 use self::texture::Texture;
-use self::mesh::Mesh;
+use self::sampler::SamplerOptions;
+use self::drawable::{DrawCall, Drawable};
+use self::mesh_pool::{MeshPool, SubMesh};
+use self::context::Context;
+use self::frame::Frame;
+use self::index::Index;
 
 use std::marker::PhantomData;
 
-pub struct Renderer<UT, V> {
-    pub(super) device: wgpu::Device,
-    pub(super) queue: wgpu::Queue,
+pub struct Renderer<UT, V, I: Index = u16> {
     pub(super) bind_group: wgpu::BindGroup,
     pub(super) bind_group_layout: wgpu::BindGroupLayout,
     pub(super) pipeline: wgpu::RenderPipeline,
-    pub(super) swap_chain: wgpu::SwapChain,
     pub(super) uniform_buffer: UniformBuffer<UT>,
     pub(super) uniform_location: u32,
     pub(super) textures: Vec<(u32, wgpu::TextureView)>,
-    pub(super) sampler: wgpu::Sampler,
-    pub(super) sampler_location: u32,
-    pub(super) phantom: PhantomData<V>
+    pub(super) samplers: Vec<(u32, wgpu::Sampler)>,
+    pub(super) phantom: PhantomData<(V, I)>
 }
 
-impl<UT, V: Copy> Renderer<UT, V> {
-    pub fn render(&mut self, mesh: &Mesh<V>) {
+impl<UT, V: Copy, I: Index> Renderer<UT, V, I> {
+    pub fn render<D: Drawable<V, I>>(&mut self, frame: &mut Frame, mesh: &D) {
+        self.draw(frame, &[mesh.draw_call()]);
+    }
+
+    /// Draws several submeshes of a pool in one render pass, binding the shared buffers once.
+    pub fn render_batch(&mut self, frame: &mut Frame, pool: &MeshPool<V, I>, submeshes: &[SubMesh]) where V: 'static {
+        let draw_calls: Vec<DrawCall> = submeshes.iter()
+            .map(|submesh| pool.submesh_draw_call(*submesh))
+            .collect();
+        self.draw(frame, &draw_calls);
+    }
+
+    fn draw(&mut self, frame: &mut Frame, draw_calls: &[DrawCall]) {
         while !self.uniform_buffer.unmapped.load(std::sync::atomic::Ordering::SeqCst) {
-            self.queue.submit(&[]);
+            frame.queue.submit(&[]);
         }
 
-        let frame = self.swap_chain.get_next_texture();
-
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        let load_op = frame.load_op();
+        let mut encoder = frame.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
 
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[
                     wgpu::RenderPassColorAttachmentDescriptor {
-                        attachment: &frame.view,
+                        attachment: &frame.output.view,
                         resolve_target: None,
-                        load_op: wgpu::LoadOp::Clear,
+                        load_op,
                         store_op: wgpu::StoreOp::Store,
                         clear_color: wgpu::Color {
                             r: 0.0,
@@ -55,11 +79,27 @@ impl<UT, V: Copy> Renderer<UT, V> {
             });
             rpass.set_pipeline(&self.pipeline);
             rpass.set_bind_group(0, &self.bind_group, &[]);
-            rpass.set_index_buffer(&mesh.index_buf, 0);
-            rpass.set_vertex_buffers(0, &[(&mesh.vertex_buf, 0)]);
-            rpass.draw_indexed(0..mesh.index_count as u32, 0, 0..1);
+
+            let mut bound_vertex_buf: Option<&wgpu::Buffer> = None;
+            let mut bound_index_buf: Option<&wgpu::Buffer> = None;
+            for draw_call in draw_calls {
+                if bound_vertex_buf.is_none_or(|buf| !std::ptr::eq(buf, draw_call.vertex_buf)) {
+                    rpass.set_vertex_buffers(0, &[(draw_call.vertex_buf, 0)]);
+                    bound_vertex_buf = Some(draw_call.vertex_buf);
+                }
+                match draw_call.index_buf {
+                    Some(index_buf) => {
+                        if bound_index_buf.is_none_or(|buf| !std::ptr::eq(buf, index_buf)) {
+                            rpass.set_index_buffer(index_buf, 0);
+                            bound_index_buf = Some(index_buf);
+                        }
+                        rpass.draw_indexed(draw_call.indices.clone(), draw_call.base_vertex, 0..1);
+                    },
+                    None => rpass.draw(draw_call.vertices.clone(), 0..1)
+                }
+            }
         }
-        self.queue.submit(&[encoder.finish()]);
+        frame.queue.submit(&[encoder.finish()]);
     }
 
     pub fn fill_uniform_buffer(&mut self, value: &UT) {
@@ -80,7 +120,7 @@ impl<UT, V: Copy> Renderer<UT, V> {
             });
     }
 
-    fn recreate_bind_group(&mut self) {
+    fn recreate_bind_group(&mut self, context: &Context) {
         let mut bindings = vec![
             wgpu::Binding {
                 binding: self.uniform_location,
@@ -100,25 +140,39 @@ impl<UT, V: Copy> Renderer<UT, V> {
             )
         }
 
-        bindings.push(
-            wgpu::Binding {
-                binding: self.sampler_location,
-                resource: wgpu::BindingResource::Sampler(&self.sampler)
-            }
-        );
+        for sampler_data in &self.samplers {
+            bindings.push(
+                wgpu::Binding {
+                    binding: sampler_data.0,
+                    resource: wgpu::BindingResource::Sampler(&sampler_data.1)
+                }
+            )
+        }
 
-        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &self.bind_group_layout,
             bindings: &bindings
         });
         self.bind_group = bind_group;
     }
 
-    pub fn bind_texture(&mut self, location: u32, texture: &Texture) {
+    /// Replaces the texture at `location`. Its view dimension has to match the binding's.
+    pub fn bind_texture(&mut self, location: u32, texture: &Texture, context: &Context) {
         for t in &mut self.textures {
             if t.0 == location {
                 t.1 = texture.get_view();
-                self.recreate_bind_group();
+                self.recreate_bind_group(context);
+                return;
+            }
+        }
+    }
+
+    /// Replaces the sampler at `location` with a new one made from `options`.
+    pub fn bind_sampler(&mut self, location: u32, options: &SamplerOptions, context: &Context) {
+        for s in &mut self.samplers {
+            if s.0 == location {
+                s.1 = options.create_sampler(&context.device);
+                self.recreate_bind_group(context);
                 return;
             }
         }
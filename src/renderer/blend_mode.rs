@@ -0,0 +1,50 @@
+#[derive(Clone, Debug)]
+pub enum BlendMode {
+    Replace,
+    Alpha,
+    PremultipliedAlpha,
+    Additive,
+    Multiply,
+    Custom {
+        color: wgpu::BlendDescriptor,
+        alpha: wgpu::BlendDescriptor
+    }
+}
+
+fn descriptor(src_factor: wgpu::BlendFactor, dst_factor: wgpu::BlendFactor) -> wgpu::BlendDescriptor {
+    wgpu::BlendDescriptor {
+        src_factor,
+        dst_factor,
+        operation: wgpu::BlendOperation::Add
+    }
+}
+
+impl BlendMode {
+    /// Returns the color and alpha blend descriptors of this mode, in that order.
+    pub(super) fn descriptors(&self) -> (wgpu::BlendDescriptor, wgpu::BlendDescriptor) {
+        use wgpu::BlendFactor::*;
+        match self {
+            BlendMode::Replace => (
+                wgpu::BlendDescriptor::REPLACE,
+                wgpu::BlendDescriptor::REPLACE
+            ),
+            BlendMode::Alpha => (
+                descriptor(SrcAlpha, OneMinusSrcAlpha),
+                descriptor(One, OneMinusSrcAlpha)
+            ),
+            BlendMode::PremultipliedAlpha => (
+                descriptor(One, OneMinusSrcAlpha),
+                descriptor(One, OneMinusSrcAlpha)
+            ),
+            BlendMode::Additive => (
+                descriptor(SrcAlpha, One),
+                descriptor(Zero, One)
+            ),
+            BlendMode::Multiply => (
+                descriptor(DstColor, Zero),
+                descriptor(DstAlpha, Zero)
+            ),
+            BlendMode::Custom { color, alpha } => (color.clone(), alpha.clone())
+        }
+    }
+}
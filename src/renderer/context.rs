@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use winit::window::{Window, WindowId};
+use winit::dpi::PhysicalSize;
+
+use crate::renderer::frame::Frame;
+use crate::renderer::window_surface::WindowSurface;
+
+pub struct Context {
+    pub(super) adapter_info: wgpu::AdapterInfo,
+    pub(super) device: wgpu::Device,
+    pub(super) queue: wgpu::Queue,
+    pub(super) surfaces: HashMap<WindowId, WindowSurface>,
+    pub(super) swap_chain_format: wgpu::TextureFormat,
+    pub(super) present_mode: wgpu::PresentMode
+}
+
+impl Context {
+    pub fn get_adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    pub fn get_present_mode(&self) -> wgpu::PresentMode {
+        self.present_mode
+    }
+
+    pub fn get_swap_chain_format(&self) -> wgpu::TextureFormat {
+        self.swap_chain_format
+    }
+
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        // wgpu::PresentMode doesn't implement PartialEq.
+        if self.present_mode as u32 == present_mode as u32 {
+            return;
+        }
+        self.present_mode = present_mode;
+        for surface in self.surfaces.values_mut() {
+            surface.sc_desc.present_mode = present_mode;
+            surface.recreate_swap_chain(&self.device);
+        }
+    }
+
+    /// Creates a surface and swap chain for an additional window on this context's device.
+    /// Adding a window that is already known recreates its swap chain.
+    pub fn add_window(&mut self, window: &Window) {
+        let surface = WindowSurface::new(window, &self.device, self.swap_chain_format, self.present_mode);
+        self.surfaces.insert(window.id(), surface);
+    }
+
+    /// Drops the swap chain and surface of a window, call this before the window is closed.
+    pub fn remove_window(&mut self, window: WindowId) -> bool {
+        self.surfaces.remove(&window).is_some()
+    }
+
+    pub fn has_window(&self, window: WindowId) -> bool {
+        self.surfaces.contains_key(&window)
+    }
+
+    pub fn resize(&mut self, window: WindowId, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        if let Some(surface) = self.surfaces.get_mut(&window) {
+            surface.sc_desc.width = size.width;
+            surface.sc_desc.height = size.height;
+            surface.recreate_swap_chain(&self.device);
+        }
+    }
+
+    pub fn begin_frame(&mut self, window: WindowId) -> Result<Frame<'_>, &'static str> {
+        let surface = match self.surfaces.get_mut(&window) {
+            Some(surface) => surface,
+            None => return Err("Window was not added to the context!")
+        };
+        Ok(Frame {
+            device: &self.device,
+            queue: &mut self.queue,
+            output: surface.swap_chain.get_next_texture(),
+            cleared: false
+        })
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use super::context::Context;
+use super::window_surface::WindowSurface;
+
+pub struct ContextBuilder<'a> {
+    window: &'a winit::window::Window,
+    power_preference: wgpu::PowerPreference,
+    backends: wgpu::BackendBit,
+    force_fallback_adapter: bool,
+    extensions: wgpu::Extensions,
+    limits: wgpu::Limits,
+    present_mode: wgpu::PresentMode,
+    swap_chain_format: wgpu::TextureFormat
+}
+
+impl<'a> ContextBuilder<'a> {
+    pub fn new(window: &winit::window::Window) -> ContextBuilder<'_> {
+        ContextBuilder {
+            window,
+            power_preference: wgpu::PowerPreference::Default,
+            backends: wgpu::BackendBit::PRIMARY,
+            force_fallback_adapter: false,
+            extensions: wgpu::Extensions {
+                anisotropic_filtering: false
+            },
+            limits: wgpu::Limits::default(),
+            present_mode: wgpu::PresentMode::Vsync,
+            swap_chain_format: wgpu::TextureFormat::Bgra8UnormSrgb
+        }
+    }
+
+    pub fn set_power_preference(mut self, power_preference: wgpu::PowerPreference) -> ContextBuilder<'a> {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn set_backends(mut self, backends: wgpu::BackendBit) -> ContextBuilder<'a> {
+        self.backends = backends;
+        self
+    }
+
+    /// wgpu doesn't expose a software adapter, so forcing the fallback requests an adapter
+    /// from the secondary backends (GL, DX11) instead of the primary ones. Secondary backends
+    /// picked with `set_backends` are kept, otherwise all of them are tried.
+    pub fn set_force_fallback_adapter(mut self, force_fallback_adapter: bool) -> ContextBuilder<'a> {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn set_extensions(mut self, extensions: wgpu::Extensions) -> ContextBuilder<'a> {
+        self.extensions = extensions;
+        self
+    }
+
+    pub fn set_limits(mut self, limits: wgpu::Limits) -> ContextBuilder<'a> {
+        self.limits = limits;
+        self
+    }
+
+    pub fn set_present_mode(mut self, present_mode: wgpu::PresentMode) -> ContextBuilder<'a> {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn set_swap_chain_format(mut self, format: wgpu::TextureFormat) -> ContextBuilder<'a> {
+        self.swap_chain_format = format;
+        self
+    }
+
+    pub fn build(self) -> Result<Context, &'static str> {
+        match self.swap_chain_format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {},
+            _ => return Err("Swap chain format must be Bgra8Unorm or Bgra8UnormSrgb!")
+        }
+
+        let backends = if self.force_fallback_adapter {
+            match self.backends & wgpu::BackendBit::SECONDARY {
+                secondary if secondary.is_empty() => wgpu::BackendBit::SECONDARY,
+                secondary => secondary
+            }
+        } else {
+            self.backends
+        };
+        if backends.is_empty() {
+            return Err("No backends left to request an adapter from!");
+        }
+
+        let adapter = match wgpu::Adapter::request(
+            &wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference,
+                backends
+            }
+        ) {
+            Some(adapter) => adapter,
+            None => return Err("No suitable adapter was found!")
+        };
+        let adapter_info = adapter.get_info();
+
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                extensions: self.extensions,
+                limits: self.limits
+            }
+        );
+
+        let mut surfaces = HashMap::new();
+        surfaces.insert(
+            self.window.id(),
+            WindowSurface::new(self.window, &device, self.swap_chain_format, self.present_mode)
+        );
+
+        Ok(Context {
+            adapter_info,
+            device,
+            queue,
+            surfaces,
+            swap_chain_format: self.swap_chain_format,
+            present_mode: self.present_mode
+        })
+    }
+}
@@ -0,0 +1,16 @@
+use std::ops::Range;
+
+use crate::renderer::index::Index;
+
+/// Buffers and ranges a renderer needs to issue a single draw.
+pub struct DrawCall<'a> {
+    pub(super) vertex_buf: &'a wgpu::Buffer,
+    pub(super) index_buf: Option<&'a wgpu::Buffer>,
+    pub(super) vertices: Range<u32>,
+    pub(super) indices: Range<u32>,
+    pub(super) base_vertex: i32
+}
+
+pub trait Drawable<V, I: Index> {
+    fn draw_call(&self) -> DrawCall<'_>;
+}
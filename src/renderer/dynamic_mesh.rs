@@ -0,0 +1,238 @@
+use std::marker::PhantomData;
+
+use crate::renderer::context::Context;
+use crate::renderer::drawable::{DrawCall, Drawable};
+use crate::renderer::index::Index;
+
+/// wgpu only copies between buffers at offsets and sizes that are multiples of this.
+const COPY_ALIGNMENT: usize = 4;
+
+/// A mesh whose vertex and index buffers can be rewritten after creation. Writes past the
+/// current capacity grow the buffers, keeping the data already uploaded.
+pub struct DynamicMesh<V: Copy + Clone, I: Index = u16> {
+    vertex_buf: wgpu::Buffer,
+    index_buf: wgpu::Buffer,
+    // Copies of the uploaded bytes, so writes can be padded to `COPY_ALIGNMENT` with the
+    // neighbouring elements instead of clobbering them.
+    vertex_data: Vec<u8>,
+    index_data: Vec<u8>,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    vertex_count: usize,
+    index_count: usize,
+    indexed: bool,
+    phantom: PhantomData<(V, I)>
+}
+
+fn create_buffer<T>(capacity: usize, usage: wgpu::BufferUsage, context: &Context) -> wgpu::Buffer {
+    let size = (capacity.max(1) * std::mem::size_of::<T>()).next_multiple_of(COPY_ALIGNMENT);
+    context.device.create_buffer(&wgpu::BufferDescriptor {
+        size: size as wgpu::BufferAddress,
+        usage: usage | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC
+    })
+}
+
+/// Records a copy of `data` into `buffer` at element `offset`. The copied range is widened
+/// to `COPY_ALIGNMENT` and filled from `shadow`, which holds the buffer's current bytes.
+fn write_buffer<T: Copy>(
+    buffer: &wgpu::Buffer,
+    shadow: &mut Vec<u8>,
+    offset: usize,
+    data: &[T],
+    encoder: &mut wgpu::CommandEncoder,
+    context: &Context
+) {
+    if data.is_empty() {
+        return;
+    }
+    let bytes = unsafe {
+        std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
+    };
+    let start = offset * std::mem::size_of::<T>();
+    let end = start + bytes.len();
+    if shadow.len() < end {
+        shadow.resize(end, 0);
+    }
+    shadow[start..end].copy_from_slice(bytes);
+
+    let copy_start = start - start % COPY_ALIGNMENT;
+    let copy_end = end.next_multiple_of(COPY_ALIGNMENT);
+    let mut padded = shadow[copy_start..copy_end.min(shadow.len())].to_vec();
+    padded.resize(copy_end - copy_start, 0);
+
+    let staging_buf = context.device.create_buffer_mapped(
+        padded.len(),
+        wgpu::BufferUsage::COPY_SRC
+    ).fill_from_slice(&padded);
+    encoder.copy_buffer_to_buffer(
+        &staging_buf,
+        0,
+        buffer,
+        copy_start as wgpu::BufferAddress,
+        padded.len() as wgpu::BufferAddress
+    );
+}
+
+/// Records a copy of the first `used` elements of `buffer` into a new, larger buffer.
+fn grow_buffer<T>(
+    buffer: &wgpu::Buffer,
+    used: usize,
+    capacity: usize,
+    usage: wgpu::BufferUsage,
+    encoder: &mut wgpu::CommandEncoder,
+    context: &Context
+) -> wgpu::Buffer {
+    let new_buf = create_buffer::<T>(capacity, usage, context);
+    if used > 0 {
+        encoder.copy_buffer_to_buffer(
+            buffer,
+            0,
+            &new_buf,
+            0,
+            (used * std::mem::size_of::<T>()).next_multiple_of(COPY_ALIGNMENT) as wgpu::BufferAddress
+        );
+    }
+    new_buf
+}
+
+fn create_encoder(context: &Context) -> wgpu::CommandEncoder {
+    context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 })
+}
+
+impl<V: Copy + Clone + 'static, I: Index> DynamicMesh<V, I> {
+    pub fn new(vertex_capacity: usize, index_capacity: usize, context: &Context) -> DynamicMesh<V, I> {
+        DynamicMesh {
+            vertex_buf: create_buffer::<V>(vertex_capacity, wgpu::BufferUsage::VERTEX, context),
+            index_buf: create_buffer::<I>(index_capacity, wgpu::BufferUsage::INDEX, context),
+            vertex_data: Vec::new(),
+            index_data: Vec::new(),
+            vertex_capacity,
+            index_capacity,
+            vertex_count: 0,
+            index_count: 0,
+            indexed: index_capacity > 0,
+            phantom: PhantomData
+        }
+    }
+
+    pub fn get_vertex_capacity(&self) -> usize {
+        self.vertex_capacity
+    }
+
+    pub fn get_index_capacity(&self) -> usize {
+        self.index_capacity
+    }
+
+    pub fn get_vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn get_index_count(&self) -> usize {
+        self.index_count
+    }
+
+    /// Meshes created with an index capacity of zero are drawn without indices until
+    /// indices are written.
+    pub fn set_indexed(&mut self, indexed: bool) {
+        self.indexed = indexed;
+    }
+
+    pub fn reserve_vertices(&mut self, capacity: usize, context: &mut Context) {
+        let mut encoder = create_encoder(context);
+        self.record_reserve_vertices(capacity, &mut encoder, context);
+        context.queue.submit(&[encoder.finish()]);
+    }
+
+    pub fn reserve_indices(&mut self, capacity: usize, context: &mut Context) {
+        let mut encoder = create_encoder(context);
+        self.record_reserve_indices(capacity, &mut encoder, context);
+        context.queue.submit(&[encoder.finish()]);
+    }
+
+    /// Overwrites the vertices starting at `offset`, extending the vertex count if the
+    /// range reaches past it.
+    pub fn update_vertices(&mut self, offset: usize, vertices: &[V], context: &mut Context) {
+        let mut encoder = create_encoder(context);
+        self.record_vertices(offset, vertices, &mut encoder, context);
+        context.queue.submit(&[encoder.finish()]);
+    }
+
+    /// Overwrites the indices starting at `offset`, extending the index count if the
+    /// range reaches past it.
+    pub fn update_indices(&mut self, offset: usize, indices: &[I], context: &mut Context) {
+        let mut encoder = create_encoder(context);
+        self.record_indices(offset, indices, &mut encoder, context);
+        context.queue.submit(&[encoder.finish()]);
+    }
+
+    /// Overwrites vertices and indices together, submitting a single batch of copies.
+    pub fn update(&mut self, vertex_offset: usize, vertices: &[V], index_offset: usize, indices: &[I], context: &mut Context) {
+        let mut encoder = create_encoder(context);
+        self.record_vertices(vertex_offset, vertices, &mut encoder, context);
+        self.record_indices(index_offset, indices, &mut encoder, context);
+        context.queue.submit(&[encoder.finish()]);
+    }
+
+    pub fn set_vertices(&mut self, vertices: &[V], context: &mut Context) {
+        self.vertex_count = 0;
+        self.update_vertices(0, vertices, context);
+    }
+
+    pub fn set_indices(&mut self, indices: &[I], context: &mut Context) {
+        self.index_count = 0;
+        self.update_indices(0, indices, context);
+    }
+
+    pub fn truncate_vertices(&mut self, count: usize) {
+        self.vertex_count = self.vertex_count.min(count);
+    }
+
+    pub fn truncate_indices(&mut self, count: usize) {
+        self.index_count = self.index_count.min(count);
+    }
+
+    fn record_reserve_vertices(&mut self, capacity: usize, encoder: &mut wgpu::CommandEncoder, context: &Context) {
+        if capacity <= self.vertex_capacity {
+            return;
+        }
+        let capacity = capacity.max(self.vertex_capacity * 2);
+        self.vertex_buf = grow_buffer::<V>(&self.vertex_buf, self.vertex_count, capacity, wgpu::BufferUsage::VERTEX, encoder, context);
+        self.vertex_capacity = capacity;
+    }
+
+    fn record_reserve_indices(&mut self, capacity: usize, encoder: &mut wgpu::CommandEncoder, context: &Context) {
+        if capacity <= self.index_capacity {
+            return;
+        }
+        let capacity = capacity.max(self.index_capacity * 2);
+        self.index_buf = grow_buffer::<I>(&self.index_buf, self.index_count, capacity, wgpu::BufferUsage::INDEX, encoder, context);
+        self.index_capacity = capacity;
+    }
+
+    fn record_vertices(&mut self, offset: usize, vertices: &[V], encoder: &mut wgpu::CommandEncoder, context: &Context) {
+        let end = offset + vertices.len();
+        self.record_reserve_vertices(end, encoder, context);
+        write_buffer(&self.vertex_buf, &mut self.vertex_data, offset, vertices, encoder, context);
+        self.vertex_count = self.vertex_count.max(end);
+    }
+
+    fn record_indices(&mut self, offset: usize, indices: &[I], encoder: &mut wgpu::CommandEncoder, context: &Context) {
+        let end = offset + indices.len();
+        self.record_reserve_indices(end, encoder, context);
+        write_buffer(&self.index_buf, &mut self.index_data, offset, indices, encoder, context);
+        self.index_count = self.index_count.max(end);
+        self.indexed = true;
+    }
+}
+
+impl<V: Copy + Clone, I: Index> Drawable<V, I> for DynamicMesh<V, I> {
+    fn draw_call(&self) -> DrawCall<'_> {
+        DrawCall {
+            vertex_buf: &self.vertex_buf,
+            index_buf: if self.indexed { Some(&self.index_buf) } else { None },
+            vertices: 0..self.vertex_count as u32,
+            indices: 0..self.index_count as u32,
+            base_vertex: 0
+        }
+    }
+}
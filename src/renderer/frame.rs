@@ -0,0 +1,19 @@
+/// A swap chain image being drawn to. Any number of renderers created from the same
+/// context can draw into it; the image is presented when the frame is dropped.
+pub struct Frame<'a> {
+    pub(super) device: &'a wgpu::Device,
+    pub(super) queue: &'a mut wgpu::Queue,
+    pub(super) output: wgpu::SwapChainOutput<'a>,
+    pub(super) cleared: bool
+}
+
+impl<'a> Frame<'a> {
+    pub(super) fn load_op(&mut self) -> wgpu::LoadOp {
+        if self.cleared {
+            wgpu::LoadOp::Load
+        } else {
+            self.cleared = true;
+            wgpu::LoadOp::Clear
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+pub trait Index: Copy + Clone + 'static {
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl Index for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+}
+
+impl Index for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+}
+
+pub fn widen_indices(indices: &[u16]) -> Vec<u32> {
+    indices.iter().map(|&index| index as u32).collect()
+}
+
+/// Splits a triangle list with 32-bit indices into chunks that each reference at most
+/// 65536 vertices, so they can be drawn with 16-bit indices.
+pub fn split_indices<V: Copy>(vertices: &[V], indices: &[u32]) -> Vec<(Vec<V>, Vec<u16>)> {
+    let max_vertices = u16::MAX as usize + 1;
+
+    let mut chunks = Vec::new();
+    let mut chunk_vertices = Vec::new();
+    let mut chunk_indices = Vec::new();
+    let mut remap: HashMap<u32, u16> = HashMap::new();
+
+    for triangle in indices.chunks(3) {
+        let new_vertices = triangle.iter()
+            .filter(|&&index| !remap.contains_key(&index))
+            .count();
+        if chunk_vertices.len() + new_vertices > max_vertices {
+            chunks.push((chunk_vertices, chunk_indices));
+            chunk_vertices = Vec::new();
+            chunk_indices = Vec::new();
+            remap.clear();
+        }
+
+        for &index in triangle {
+            let local = *remap.entry(index).or_insert_with(|| {
+                chunk_vertices.push(vertices[index as usize]);
+                (chunk_vertices.len() - 1) as u16
+            });
+            chunk_indices.push(local);
+        }
+    }
+
+    if !chunk_indices.is_empty() {
+        chunks.push((chunk_vertices, chunk_indices));
+    }
+    chunks
+}
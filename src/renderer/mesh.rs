@@ -1,28 +1,78 @@
-use crate::renderer::Renderer;
+use crate::renderer::context::Context;
+use crate::renderer::drawable::{DrawCall, Drawable};
+use crate::renderer::index::{self, Index};
 use std::marker::PhantomData;
 
-pub struct Mesh<V: Copy + Clone> {
+pub struct Mesh<V: Copy + Clone, I: Index = u16> {
     pub(super) vertex_buf: wgpu::Buffer,
-    pub(super) index_buf: wgpu::Buffer,
+    pub(super) index_buf: Option<wgpu::Buffer>,
+    pub(super) vertex_count: u64,
     pub(super) index_count: u64,
-    phantom: PhantomData<V>
+    phantom: PhantomData<(V, I)>
 }
 
-impl<V: Copy + Clone + 'static> Mesh<V> {
-    pub fn new<UT>(vertices: Vec<V>, indices: Vec<u16>, renderer: &Renderer<UT, V>) -> Mesh<V> {
-        let vertex_buf = renderer.device.create_buffer_mapped(
+impl<V: Copy + Clone + 'static, I: Index> Mesh<V, I> {
+    pub fn new(vertices: Vec<V>, indices: Vec<I>, context: &Context) -> Mesh<V, I> {
+        let vertex_buf = context.device.create_buffer_mapped(
             vertices.len(),
             wgpu::BufferUsage::VERTEX
         ).fill_from_slice(&vertices);
-        let index_buf = renderer.device.create_buffer_mapped(
+        let index_buf = context.device.create_buffer_mapped(
             indices.len(),
             wgpu::BufferUsage::INDEX
         ).fill_from_slice(&indices);
         Mesh {
             vertex_buf,
-            index_buf,
+            index_buf: Some(index_buf),
+            vertex_count: vertices.len() as u64,
             index_count: indices.len() as u64,
             phantom: PhantomData
         }
     }
-}
\ No newline at end of file
+
+    pub fn new_non_indexed(vertices: Vec<V>, context: &Context) -> Mesh<V, I> {
+        let vertex_buf = context.device.create_buffer_mapped(
+            vertices.len(),
+            wgpu::BufferUsage::VERTEX
+        ).fill_from_slice(&vertices);
+        Mesh {
+            vertex_buf,
+            index_buf: None,
+            vertex_count: vertices.len() as u64,
+            index_count: 0,
+            phantom: PhantomData
+        }
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.index_buf.is_some()
+    }
+}
+
+impl<V: Copy + Clone, I: Index> Drawable<V, I> for Mesh<V, I> {
+    fn draw_call(&self) -> DrawCall<'_> {
+        DrawCall {
+            vertex_buf: &self.vertex_buf,
+            index_buf: self.index_buf.as_ref(),
+            vertices: 0..self.vertex_count as u32,
+            indices: 0..self.index_count as u32,
+            base_vertex: 0
+        }
+    }
+}
+
+impl<V: Copy + Clone + 'static> Mesh<V, u32> {
+    pub fn new_widened(vertices: Vec<V>, indices: Vec<u16>, context: &Context) -> Mesh<V, u32> {
+        Mesh::new(vertices, index::widen_indices(&indices), context)
+    }
+}
+
+impl<V: Copy + Clone + 'static> Mesh<V, u16> {
+    /// Uploads a triangle list that is too large for 16-bit indices as several meshes.
+    pub fn new_split(vertices: Vec<V>, indices: Vec<u32>, context: &Context) -> Vec<Mesh<V, u16>> {
+        index::split_indices(&vertices, &indices)
+            .into_iter()
+            .map(|(vertices, indices)| Mesh::new(vertices, indices, context))
+            .collect()
+    }
+}
@@ -0,0 +1,85 @@
+use crate::renderer::context::Context;
+use crate::renderer::drawable::{DrawCall, Drawable};
+use crate::renderer::dynamic_mesh::DynamicMesh;
+use crate::renderer::index::Index;
+
+/// A range of indices inside a `MeshPool`, drawn relative to `base_vertex`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SubMesh {
+    pub(super) first_index: u32,
+    pub(super) index_count: u32,
+    pub(super) base_vertex: i32
+}
+
+impl SubMesh {
+    pub fn get_index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    /// Narrows the submesh down to `count` indices starting at `first`, relative to its own
+    /// first index.
+    pub fn slice(&self, first: u32, count: u32) -> SubMesh {
+        let first = first.min(self.index_count);
+        SubMesh {
+            first_index: self.first_index + first,
+            index_count: count.min(self.index_count - first),
+            base_vertex: self.base_vertex
+        }
+    }
+}
+
+/// Packs many small meshes into one shared vertex and index buffer, so they can be drawn
+/// without switching buffers.
+pub struct MeshPool<V: Copy + Clone, I: Index = u16> {
+    pub(super) mesh: DynamicMesh<V, I>
+}
+
+pub struct PooledMesh<'a, V: Copy + Clone, I: Index> {
+    pool: &'a MeshPool<V, I>,
+    submesh: SubMesh
+}
+
+impl<V: Copy + Clone + 'static, I: Index> MeshPool<V, I> {
+    pub fn new(vertex_capacity: usize, index_capacity: usize, context: &Context) -> MeshPool<V, I> {
+        MeshPool {
+            mesh: DynamicMesh::new(vertex_capacity, index_capacity.max(1), context)
+        }
+    }
+
+    pub fn add(&mut self, vertices: &[V], indices: &[I], context: &mut Context) -> SubMesh {
+        let base_vertex = self.mesh.get_vertex_count();
+        let first_index = self.mesh.get_index_count();
+        self.mesh.update(base_vertex, vertices, first_index, indices, context);
+        SubMesh {
+            first_index: first_index as u32,
+            index_count: indices.len() as u32,
+            base_vertex: base_vertex as i32
+        }
+    }
+
+    /// Forgets every submesh, the buffers keep their capacity for reuse.
+    pub fn clear(&mut self) {
+        self.mesh.truncate_vertices(0);
+        self.mesh.truncate_indices(0);
+    }
+
+    pub fn get(&self, submesh: SubMesh) -> PooledMesh<'_, V, I> {
+        PooledMesh {
+            pool: self,
+            submesh
+        }
+    }
+
+    pub(super) fn submesh_draw_call(&self, submesh: SubMesh) -> DrawCall<'_> {
+        let mut draw_call = self.mesh.draw_call();
+        draw_call.indices = submesh.first_index..submesh.first_index + submesh.index_count;
+        draw_call.base_vertex = submesh.base_vertex;
+        draw_call
+    }
+}
+
+impl<'a, V: Copy + Clone + 'static, I: Index> Drawable<V, I> for PooledMesh<'a, V, I> {
+    fn draw_call(&self) -> DrawCall<'_> {
+        self.pool.submesh_draw_call(self.submesh)
+    }
+}
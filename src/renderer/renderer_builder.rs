@@ -6,30 +6,46 @@ use crate::renderer::shader::Shader;
 use crate::renderer::uniform_buffer::UniformBuffer;
 
 use super::Renderer;
+use super::index::Index;
+use super::blend_mode::BlendMode;
+use super::context::Context;
 use super::texture::Texture;
+use super::sampler::SamplerOptions;
 
 pub struct RendererBuilder<'a> {
-    window: &'a winit::window::Window,
     vs: Option<&'a Shader>,
     fs: Option<&'a Shader>,
     uniform_location: u32,
-    textures: Vec<u32>,
+    textures: Vec<(u32, wgpu::TextureViewDimension)>,
     culling: (wgpu::FrontFace, wgpu::CullMode),
     vertex_attributes: Vec<(u32, wgpu::VertexFormat)>,
-    sampler_location: u32
+    sampler_location: u32,
+    samplers: Vec<(u32, SamplerOptions)>,
+    blend_mode: BlendMode,
+    write_mask: wgpu::ColorWrite,
+    primitive_topology: wgpu::PrimitiveTopology
+}
+
+impl<'a> Default for RendererBuilder<'a> {
+    fn default() -> RendererBuilder<'a> {
+        RendererBuilder::new()
+    }
 }
 
 impl<'a> RendererBuilder<'a> {
-    pub fn new(window: &winit::window::Window) -> RendererBuilder {
+    pub fn new() -> RendererBuilder<'a> {
         RendererBuilder {
-            window,
             vs: None,
             fs: None,
             uniform_location: 0,
             textures: Vec::new(),
             culling: (wgpu::FrontFace::Ccw, wgpu::CullMode::None),
             vertex_attributes: Vec::new(),
-            sampler_location: 1
+            sampler_location: 1,
+            samplers: Vec::new(),
+            blend_mode: BlendMode::Replace,
+            write_mask: wgpu::ColorWrite::ALL,
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList
         }
     }
 
@@ -44,15 +60,45 @@ impl<'a> RendererBuilder<'a> {
     }
 
     pub fn add_texture(mut self, location: u32) -> RendererBuilder<'a> {
-        self.textures.push(location);
+        self.textures.push((location, wgpu::TextureViewDimension::D2));
         self
     }
 
+    /// Adds a texture binding of any dimension. Textures bound to it later have to have the
+    /// same view dimension.
+    pub fn add_texture_with_dimension(mut self, location: u32, dimension: wgpu::TextureViewDimension) -> RendererBuilder<'a> {
+        self.textures.push((location, dimension));
+        self
+    }
+
+    /// Adds a cube map binding, sampled with `samplerCube` and a direction.
+    pub fn add_cube_texture(self, location: u32) -> RendererBuilder<'a> {
+        self.add_texture_with_dimension(location, wgpu::TextureViewDimension::Cube)
+    }
+
+    /// Adds a texture array binding, sampled with `sampler2DArray` and the layer as the third
+    /// coordinate.
+    pub fn add_array_texture(self, location: u32) -> RendererBuilder<'a> {
+        self.add_texture_with_dimension(location, wgpu::TextureViewDimension::D2Array)
+    }
+
+    /// Adds a 3D texture binding, sampled with `sampler3D`.
+    pub fn add_3d_texture(self, location: u32) -> RendererBuilder<'a> {
+        self.add_texture_with_dimension(location, wgpu::TextureViewDimension::D3)
+    }
+
     pub fn set_sampler_location(mut self, sampler_location: u32) -> RendererBuilder<'a> {
         self.sampler_location = sampler_location;
         self
     }
 
+    /// Adds a sampler at `location`. Once any sampler is added, the default one at the
+    /// sampler location is no longer created.
+    pub fn add_sampler(mut self, location: u32, options: SamplerOptions) -> RendererBuilder<'a> {
+        self.samplers.push((location, options));
+        self
+    }
+
     pub fn set_culling(mut self, front_face: wgpu::FrontFace, cull_mode: wgpu::CullMode) -> RendererBuilder<'a> {
         self.culling = (front_face, cull_mode);
         self
@@ -68,34 +114,27 @@ impl<'a> RendererBuilder<'a> {
         self
     }
 
-    pub fn build<UT, V>(mut self) -> Result<Renderer<UT, V>, &'static str> {
-        let size = self.window.inner_size();
-        let surface = wgpu::Surface::create(self.window);
+    pub fn set_blend_mode(mut self, blend_mode: BlendMode) -> RendererBuilder<'a> {
+        self.blend_mode = blend_mode;
+        self
+    }
 
-        let adapter = wgpu::Adapter::request(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::Default,
-                backends: wgpu::BackendBit::PRIMARY
-            }
-        ).unwrap();
-
-        let (mut device, mut queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                extensions: wgpu::Extensions {
-                    anisotropic_filtering: false
-                },
-                limits: wgpu::Limits::default()
-            }
-        );
-
-        let sc_desc = wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Vsync
-        };
-        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+    pub fn set_write_mask(mut self, write_mask: wgpu::ColorWrite) -> RendererBuilder<'a> {
+        self.write_mask = write_mask;
+        self
+    }
+
+    pub fn set_primitive_topology(mut self, primitive_topology: wgpu::PrimitiveTopology) -> RendererBuilder<'a> {
+        self.primitive_topology = primitive_topology;
+        self
+    }
+
+    pub fn build<UT, V, I: Index>(mut self, context: &mut Context) -> Result<Renderer<UT, V, I>, &'static str> {
+        let device = &context.device;
+
+        if self.samplers.is_empty() {
+            self.samplers.push((self.sampler_location, SamplerOptions::default()));
+        }
 
         let mut bindings = vec![
             wgpu::BindGroupLayoutBinding {
@@ -107,22 +146,24 @@ impl<'a> RendererBuilder<'a> {
         for texture_data in &self.textures {
             bindings.push(
                 wgpu::BindGroupLayoutBinding {
-                    binding: *texture_data,
+                    binding: texture_data.0,
                     visibility: wgpu::ShaderStage::FRAGMENT,
                     ty: wgpu::BindingType::SampledTexture {
                         multisampled: false,
-                        dimension: wgpu::TextureViewDimension::D2
+                        dimension: texture_data.1
                     }
                 }
             )
         }
-        bindings.push(
-            wgpu::BindGroupLayoutBinding {
-                binding: self.sampler_location,
-                visibility: wgpu::ShaderStage::FRAGMENT,
-                ty: wgpu::BindingType::Sampler
-            }
-        );
+        for sampler_data in &self.samplers {
+            bindings.push(
+                wgpu::BindGroupLayoutBinding {
+                    binding: sampler_data.0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler
+                }
+            )
+        }
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             bindings: &bindings
@@ -132,7 +173,7 @@ impl<'a> RendererBuilder<'a> {
             bind_group_layouts: &[&bind_group_layout]
         });
 
-        let uniform_buffer = UniformBuffer::new(&device);
+        let uniform_buffer = UniformBuffer::new(device);
 
         let mut default_texture_data = Vec::new();
         for x in 0..256 {
@@ -161,12 +202,48 @@ impl<'a> RendererBuilder<'a> {
             }
         ];
 
-        let mut textures = Vec::new();
-        let default_texture = Texture::new_from_data(256, 256, &default_texture_data, &mut device, &mut queue);
+        // Every dimension in use gets its own checkerboard to bind until a texture is set.
+        let mut default_textures: Vec<(wgpu::TextureViewDimension, Texture)> = Vec::new();
+        for texture_data in &self.textures {
+            if !default_textures.iter().any(|(dimension, _)| *dimension == texture_data.1) {
+                let layer_count = match texture_data.1 {
+                    wgpu::TextureViewDimension::D2 | wgpu::TextureViewDimension::D2Array | wgpu::TextureViewDimension::D3 => 1,
+                    wgpu::TextureViewDimension::Cube | wgpu::TextureViewDimension::CubeArray => 6,
+                    _ => return Err("Texture binding dimension isn't supported!")
+                };
+                let default_texture = if texture_data.1 == wgpu::TextureViewDimension::D3 {
+                    Texture::new_3d_from_data(
+                        256,
+                        256,
+                        1,
+                        wgpu::TextureFormat::Rgba8UnormSrgb,
+                        &default_texture_data,
+                        device,
+                        &mut context.queue
+                    )?
+                } else {
+                    Texture::new_from_levels(
+                        256,
+                        256,
+                        wgpu::TextureFormat::Rgba8UnormSrgb,
+                        &vec![vec![default_texture_data.clone()]; layer_count],
+                        texture_data.1,
+                        device,
+                        &mut context.queue
+                    )?
+                };
+                default_textures.push((texture_data.1, default_texture));
+            }
+        }
 
+        let mut textures = Vec::new();
         for texture_data in &self.textures {
+            let default_texture = default_textures.iter()
+                .find(|(dimension, _)| *dimension == texture_data.1)
+                .map(|(_, texture)| texture)
+                .unwrap();
             textures.push(
-                (*texture_data, default_texture.get_view())
+                (texture_data.0, default_texture.get_view())
             );
         }
 
@@ -179,24 +256,18 @@ impl<'a> RendererBuilder<'a> {
             );
         }
 
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            lod_min_clamp: -100.0,
-            lod_max_clamp: 100.0,
-            compare_function: wgpu::CompareFunction::Always
-        });
+        let samplers: Vec<(u32, wgpu::Sampler)> = self.samplers.iter()
+            .map(|(location, options)| (*location, options.create_sampler(device)))
+            .collect();
 
-        bindings.push(
-            wgpu::Binding {
-                binding: self.sampler_location,
-                resource: wgpu::BindingResource::Sampler(&sampler)
-            }
-        );
+        for sampler in &samplers {
+            bindings.push(
+                wgpu::Binding {
+                    binding: sampler.0,
+                    resource: wgpu::BindingResource::Sampler(&sampler.1)
+                }
+            );
+        }
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
@@ -225,7 +296,7 @@ impl<'a> RendererBuilder<'a> {
         let mut attributes = Vec::new();
         let mut cur_offset = 0;
 
-        self.vertex_attributes.sort_by(|a, b| a.0.cmp(&b.0));
+        self.vertex_attributes.sort_by_key(|attribute| attribute.0);
         for attribute in &self.vertex_attributes {
             attributes.push(
                 wgpu::VertexAttributeDescriptor {
@@ -268,6 +339,8 @@ impl<'a> RendererBuilder<'a> {
             }
         }
 
+        let (color_blend, alpha_blend) = self.blend_mode.descriptors();
+
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             layout: &pipeline_layout,
             vertex_stage,
@@ -279,17 +352,17 @@ impl<'a> RendererBuilder<'a> {
                 depth_bias_slope_scale: 0.0,
                 depth_bias_clamp: 0.0
             }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            primitive_topology: self.primitive_topology,
             color_states: &[
                 wgpu::ColorStateDescriptor {
-                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                    alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                    color_blend: wgpu::BlendDescriptor::REPLACE,
-                    write_mask: wgpu::ColorWrite::ALL
+                    format: context.swap_chain_format,
+                    alpha_blend,
+                    color_blend,
+                    write_mask: self.write_mask
                 }
             ],
             depth_stencil_state: None,
-            index_format: wgpu::IndexFormat::Uint16,
+            index_format: I::FORMAT,
             vertex_buffers: &[
                 wgpu::VertexBufferDescriptor {
                     stride: cur_offset as wgpu::BufferAddress,
@@ -303,17 +376,13 @@ impl<'a> RendererBuilder<'a> {
         });
 
         Ok(Renderer {
-            device,
-            queue,
             bind_group,
             bind_group_layout,
             pipeline,
-            swap_chain,
             uniform_buffer,
             uniform_location: self.uniform_location,
             textures,
-            sampler,
-            sampler_location: self.sampler_location,
+            samplers,
             phantom: PhantomData
         })
     }
@@ -0,0 +1,82 @@
+/// Settings of a sampler bound by a `Renderer`. The default matches the sampler renderers
+/// used to always create.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SamplerOptions {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub address_mode_w: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    /// Anything other than `Always` makes this a comparison sampler, for sampling depth
+    /// textures such as shadow maps with `sampler2DShadow`.
+    pub compare_function: wgpu::CompareFunction
+}
+
+impl Default for SamplerOptions {
+    fn default() -> SamplerOptions {
+        SamplerOptions {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare_function: wgpu::CompareFunction::Always
+        }
+    }
+}
+
+impl SamplerOptions {
+    /// Trilinear filtering with the given address mode on every axis.
+    pub fn linear(address_mode: wgpu::AddressMode) -> SamplerOptions {
+        SamplerOptions {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..SamplerOptions::default()
+        }
+    }
+
+    /// No filtering at all, for pixel art and lookup tables.
+    pub fn nearest(address_mode: wgpu::AddressMode) -> SamplerOptions {
+        SamplerOptions {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..SamplerOptions::default()
+        }
+    }
+
+    /// A linearly filtered comparison sampler clamped to the edge, as used for shadow maps.
+    pub fn comparison(compare_function: wgpu::CompareFunction) -> SamplerOptions {
+        SamplerOptions {
+            compare_function,
+            ..SamplerOptions::linear(wgpu::AddressMode::ClampToEdge)
+        }
+    }
+
+    pub(super) fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: self.address_mode_w,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare_function: self.compare_function
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,109 @@
+use shaderc::ShaderKind;
+
+use crate::math::mat4::Mat4;
+
+use super::Renderer;
+use super::context::Context;
+use super::frame::Frame;
+use super::mesh::Mesh;
+use super::renderer_builder::RendererBuilder;
+use super::sampler::SamplerOptions;
+use super::shader::Shader;
+use super::texture::Texture;
+
+const VERTEX_SHADER: &str = "
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 0) out vec3 direction;
+
+layout(set = 0, binding = 0, row_major) uniform Uniforms {
+    mat4 view;
+    mat4 projection;
+};
+
+void main() {
+    vec4 target = inverse(projection) * vec4(position, 1.0, 1.0);
+    direction = transpose(mat3(view)) * (target.xyz / target.w);
+    gl_Position = vec4(position, 1.0, 1.0);
+}
+";
+
+const FRAGMENT_SHADER: &str = "
+#version 450
+
+layout(location = 0) in vec3 direction;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 1) uniform textureCube skybox_texture;
+layout(set = 0, binding = 2) uniform sampler skybox_sampler;
+
+void main() {
+    color = texture(samplerCube(skybox_texture, skybox_sampler), normalize(direction));
+}
+";
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SkyboxUniforms {
+    view: Mat4,
+    projection: Mat4
+}
+
+/// Fills the whole frame with a cube map seen from the camera. Render it first, before
+/// anything else in the frame, so the scene is drawn over it.
+pub struct Skybox {
+    renderer: Renderer<SkyboxUniforms, [f32; 2]>,
+    mesh: Mesh<[f32; 2]>,
+    // The uniform buffer is filled asynchronously, so the values have to stay put until then.
+    uniforms: Box<SkyboxUniforms>
+}
+
+impl Skybox {
+    pub fn new(texture: &Texture, context: &mut Context) -> Result<Skybox, &'static str> {
+        let vs = Shader::new_from_source(VERTEX_SHADER, ShaderKind::Vertex);
+        let fs = Shader::new_from_source(FRAGMENT_SHADER, ShaderKind::Fragment);
+        let renderer = RendererBuilder::new()
+            .add_vertex_shader(&vs)
+            .add_fragment_shader(&fs)
+            .add_vertex_attribute(0, wgpu::VertexFormat::Float2)
+            .add_cube_texture(1)
+            .add_sampler(2, SamplerOptions::linear(wgpu::AddressMode::ClampToEdge))
+            .build(context)?;
+        // One triangle covering the screen, clipped to it.
+        let mesh = Mesh::new_non_indexed(vec![[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]], context);
+
+        let mut skybox = Skybox {
+            renderer,
+            mesh,
+            uniforms: Box::new(SkyboxUniforms {
+                view: Mat4::identity(),
+                projection: Mat4::identity()
+            })
+        };
+        skybox.set_texture(texture, context)?;
+        skybox.renderer.fill_uniform_buffer(&skybox.uniforms);
+        Ok(skybox)
+    }
+
+    pub fn set_texture(&mut self, texture: &Texture, context: &Context) -> Result<(), &'static str> {
+        if texture.get_view_dimension() != wgpu::TextureViewDimension::Cube {
+            return Err("Skybox texture has to be a cube map!");
+        }
+        self.renderer.bind_texture(1, texture, context);
+        Ok(())
+    }
+
+    /// Sets the camera. Only the rotation of `view` is used, it may not scale or shear.
+    pub fn set_view(&mut self, view: &Mat4, projection: &Mat4) {
+        *self.uniforms = SkyboxUniforms {
+            view: *view,
+            projection: *projection
+        };
+        self.renderer.fill_uniform_buffer(&self.uniforms);
+    }
+
+    pub fn render(&mut self, frame: &mut Frame) {
+        self.renderer.render(frame, &self.mesh);
+    }
+}
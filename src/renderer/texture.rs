@@ -1,72 +1,770 @@
+use std::fmt;
+use std::io::Cursor;
 use std::path::Path;
 use image::GenericImageView;
-use crate::renderer::Renderer;
+use crate::renderer::context::Context;
+use crate::renderer::texture_format;
+
+/// Rows of a buffer copied into a texture have to start at multiples of this many bytes.
+const ROW_ALIGNMENT: u32 = 256;
+
+/// Images wider or taller than this are rejected before they're decoded. wgpu 0.4 exposes no
+/// device limits to check against, so this is WebGPU's minimum for the largest 2D texture,
+/// which every device supports.
+pub const MAX_IMAGE_SIZE: u32 = 8192;
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextureOptions {
+    /// Builds the full chain of mip levels down to 1x1 by box filtering on the CPU. Only
+    /// normalized 8-bit and float formats can be filtered, others always get one level.
+    pub generate_mipmaps: bool,
+    /// Whether 8-bit RGBA images hold sRGB encoded colors. Turn this off for normal maps and
+    /// other data the shader has to read unchanged.
+    pub srgb: bool
+}
+
+impl Default for TextureOptions {
+    fn default() -> TextureOptions {
+        TextureOptions {
+            generate_mipmaps: true,
+            srgb: true
+        }
+    }
+}
+
+impl TextureOptions {
+    fn rgba_format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    UnsupportedFormat(image::ImageError),
+    TooLarge(u32, u32),
+    /// The pixels couldn't be uploaded, see the message.
+    Invalid(&'static str)
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io(error) => write!(f, "IO error: {}", error),
+            TextureError::Decode(error) => write!(f, "Decode error: {}", error),
+            TextureError::UnsupportedFormat(error) => write!(f, "Unsupported format: {}", error),
+            TextureError::TooLarge(width, height) => write!(f, "Image is too large: {}x{}, at most {}x{} is supported", width, height, MAX_IMAGE_SIZE, MAX_IMAGE_SIZE),
+            TextureError::Invalid(message) => write!(f, "Invalid texture: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Io(error) => Some(error),
+            TextureError::Decode(error) => Some(error),
+            TextureError::UnsupportedFormat(error) => Some(error),
+            TextureError::TooLarge(_, _) | TextureError::Invalid(_) => None
+        }
+    }
+}
+
+impl From<std::io::Error> for TextureError {
+    fn from(error: std::io::Error) -> TextureError {
+        TextureError::Io(error)
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(error: image::ImageError) -> TextureError {
+        match error {
+            image::ImageError::IoError(error) => TextureError::Io(error),
+            image::ImageError::Unsupported(_) => TextureError::UnsupportedFormat(error),
+            _ => TextureError::Decode(error)
+        }
+    }
+}
+
+impl From<&'static str> for TextureError {
+    fn from(message: &'static str) -> TextureError {
+        TextureError::Invalid(message)
+    }
+}
+
+pub(crate) fn check_image_size((width, height): (u32, u32)) -> Result<(), TextureError> {
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(TextureError::TooLarge(width, height));
+    }
+    Ok(())
+}
+
+/// Opens an image file after checking its size from the header.
+fn open_image(path: &Path) -> Result<image::DynamicImage, TextureError> {
+    check_image_size(image::image_dimensions(path)?)?;
+    Ok(image::open(path)?)
+}
 
 pub struct Texture {
     texture: wgpu::Texture,
-    texture_extent: wgpu::Extent3d
+    texture_extent: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+    mip_level_count: u32,
+    array_layer_count: u32,
+    view_dimension: wgpu::TextureViewDimension
+}
+
+/// Halves an image, averaging 2x2 blocks. Odd sizes repeat the last row or column.
+fn downsample(width: u32, height: u32, values: &[f32], channels: usize) -> (u32, u32, Vec<f32>) {
+    let new_width = (width / 2).max(1);
+    let new_height = (height / 2).max(1);
+    let mut result = Vec::with_capacity(new_width as usize * new_height as usize * channels);
+    for y in 0..new_height {
+        let rows = [(y * 2).min(height - 1), (y * 2 + 1).min(height - 1)];
+        for x in 0..new_width {
+            let columns = [(x * 2).min(width - 1), (x * 2 + 1).min(width - 1)];
+            for channel in 0..channels {
+                let mut sum = 0.0;
+                for &row in &rows {
+                    for &column in &columns {
+                        sum += values[(row * width + column) as usize * channels + channel];
+                    }
+                }
+                result.push(sum / 4.0);
+            }
+        }
+    }
+    (new_width, new_height, result)
+}
+
+/// The smallest multiple of the required alignment that fits `row_size` bytes.
+fn row_pitch(row_size: u32) -> u32 {
+    row_size.div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT
+}
+
+/// The levels of an image, starting with itself. With `options.generate_mipmaps` and a
+/// filterable format this is the full chain down to 1x1, otherwise just the image.
+fn mip_chain(width: u32, height: u32, format: wgpu::TextureFormat, data: &[u8], options: &TextureOptions) -> Vec<Vec<u8>> {
+    let mut levels = vec![data.to_vec()];
+    if options.generate_mipmaps {
+        if let Some((channels, encoding)) = texture_format::filterable_channels(format) {
+            let mut level = (width, height, texture_format::decode(data, channels, encoding));
+            while level.0 > 1 || level.1 > 1 {
+                level = downsample(level.0, level.1, &level.2, channels);
+                levels.push(texture_format::encode(&level.2, channels, encoding));
+            }
+        }
+    }
+    levels
+}
+
+/// The direction a cube map sampled at `(s, t)` of a face looks up, both in -1..1 with `t`
+/// pointing down. Faces are in +X, -X, +Y, -Y, +Z, -Z order.
+fn cube_direction(face: usize, s: f32, t: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0]
+    }
+}
+
+/// Resamples an equirectangular panorama into six cube faces with bilinear filtering. The
+/// middle of the panorama ends up facing -Z, its top +Y.
+fn equirectangular_to_cube(width: u32, height: u32, values: &[f32], channels: usize, face_size: u32) -> Vec<Vec<f32>> {
+    let sample = |x: i64, y: i64, channel: usize| -> f32 {
+        let x = x.rem_euclid(width as i64) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        values[(y * width as usize + x) * channels + channel]
+    };
+
+    let mut faces = Vec::with_capacity(6);
+    for face in 0..6 {
+        let mut face_values = Vec::with_capacity(face_size as usize * face_size as usize * channels);
+        for y in 0..face_size {
+            for x in 0..face_size {
+                let s = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let t = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let [dx, dy, dz] = cube_direction(face, s, t);
+                let length = (dx * dx + dy * dy + dz * dz).sqrt();
+                let u = 0.5 + dx.atan2(-dz) / (2.0 * std::f32::consts::PI);
+                let v = (dy / length).clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+
+                let px = u * width as f32 - 0.5;
+                let py = v * height as f32 - 0.5;
+                let (x0, y0) = (px.floor() as i64, py.floor() as i64);
+                let (fx, fy) = (px - px.floor(), py - py.floor());
+                for channel in 0..channels {
+                    let top = sample(x0, y0, channel) * (1.0 - fx) + sample(x0 + 1, y0, channel) * fx;
+                    let bottom = sample(x0, y0 + 1, channel) * (1.0 - fx) + sample(x0 + 1, y0 + 1, channel) * fx;
+                    face_values.push(top * (1.0 - fy) + bottom * fy);
+                }
+            }
+        }
+        faces.push(face_values);
+    }
+    faces
+}
+
+/// A box of tightly packed pixels to copy into one mip level and array layer of a texture.
+struct Upload<'a> {
+    mip_level: u32,
+    array_layer: u32,
+    origin: [u32; 3],
+    size: [u32; 3],
+    data: &'a [u8]
+}
+
+/// Copies pixels into a texture through one staging buffer, padding every row to the
+/// required alignment.
+fn copy_to_texture(texture: &wgpu::Texture, bytes_per_pixel: u32, uploads: &[Upload], device: &wgpu::Device, queue: &mut wgpu::Queue) {
+    let mut staging = Vec::new();
+    let mut offsets = Vec::with_capacity(uploads.len());
+    for upload in uploads {
+        let row_size = bytes_per_pixel * upload.size[0];
+        let row_pitch = row_pitch(row_size);
+        offsets.push((staging.len() as u64, row_pitch));
+        for row in upload.data.chunks(row_size as usize) {
+            staging.extend_from_slice(row);
+            staging.resize(staging.len() + (row_pitch - row_size) as usize, 0);
+        }
+    }
+    let temp_buf = device.create_buffer_mapped(staging.len(), wgpu::BufferUsage::COPY_SRC)
+        .fill_from_slice(&staging);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        todo: 0
+    });
+    for (upload, (offset, row_pitch)) in uploads.iter().zip(offsets) {
+        encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &temp_buf,
+                offset,
+                row_pitch,
+                image_height: upload.size[1]
+            },
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: upload.mip_level,
+                array_layer: upload.array_layer,
+                origin: wgpu::Origin3d {
+                    x: upload.origin[0] as f32,
+                    y: upload.origin[1] as f32,
+                    z: upload.origin[2] as f32
+                }
+            },
+            wgpu::Extent3d {
+                width: upload.size[0],
+                height: upload.size[1],
+                depth: upload.size[2]
+            }
+        );
+    }
+    queue.submit(&[encoder.finish()]);
 }
 
 impl Texture {
-    pub(super) fn new_from_data(width: u32, height: u32, data: &Vec<u8>, device: &mut wgpu::Device, queue: &mut wgpu::Queue) -> Texture {
+    pub(super) fn new_from_data(width: u32, height: u32, format: wgpu::TextureFormat, data: &[u8], options: &TextureOptions, device: &wgpu::Device, queue: &mut wgpu::Queue) -> Result<Texture, &'static str> {
+        let bytes_per_pixel = match texture_format::bytes_per_pixel(format) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => return Err("Texture format can't be uploaded from memory!")
+        };
+        if data.len() as u64 != width as u64 * height as u64 * bytes_per_pixel as u64 {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let levels = mip_chain(width, height, format, data, options);
+        Self::new_from_levels(width, height, format, &[levels], wgpu::TextureViewDimension::D2, device, queue)
+    }
+
+    /// Creates a texture from `layers[layer][level]`, each level tightly packed and half the
+    /// size of the one before it, rounded down to at least 1.
+    pub(super) fn new_from_levels(width: u32, height: u32, format: wgpu::TextureFormat, layers: &[Vec<Vec<u8>>], view_dimension: wgpu::TextureViewDimension, device: &wgpu::Device, queue: &mut wgpu::Queue) -> Result<Texture, &'static str> {
+        let bytes_per_pixel = match texture_format::bytes_per_pixel(format) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => return Err("Texture format can't be uploaded from memory!")
+        };
+        if width == 0 || height == 0 {
+            return Err("Texture size can't be zero!");
+        }
+        if layers.is_empty() {
+            return Err("Texture needs at least one layer!");
+        }
+        let mip_level_count = layers[0].len() as u32;
+        if mip_level_count == 0 || layers.iter().any(|levels| levels.len() as u32 != mip_level_count) {
+            return Err("Texture layers need the same, non-zero number of mip levels!");
+        }
+        if mip_level_count > 32 - width.max(height).leading_zeros() {
+            return Err("Texture has more mip levels than its size allows!");
+        }
+        let array_layer_count = layers.len() as u32;
+        let layers_match = match view_dimension {
+            wgpu::TextureViewDimension::D2 => array_layer_count == 1,
+            wgpu::TextureViewDimension::D2Array => true,
+            wgpu::TextureViewDimension::Cube => array_layer_count == 6 && width == height,
+            wgpu::TextureViewDimension::CubeArray => array_layer_count.is_multiple_of(6) && width == height,
+            _ => false
+        };
+        if !layers_match {
+            return Err("Texture layers don't match its view dimension!");
+        }
+
+        let mut uploads = Vec::with_capacity(layers.len() * mip_level_count as usize);
+        for (layer, levels) in layers.iter().enumerate() {
+            for (level, level_data) in levels.iter().enumerate() {
+                let level_width = (width >> level).max(1);
+                let level_height = (height >> level).max(1);
+                if level_data.len() as u64 != level_width as u64 * level_height as u64 * bytes_per_pixel as u64 {
+                    return Err("Texture data length doesn't match its size and format!");
+                }
+                uploads.push(Upload {
+                    mip_level: level as u32,
+                    array_layer: layer as u32,
+                    origin: [0, 0, 0],
+                    size: [level_width, level_height, 1],
+                    data: level_data
+                });
+            }
+        }
+
         let texture_extent = wgpu::Extent3d {
             width,
             height,
             depth: 1
         };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: texture_extent,
+            array_layer_count,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST
+        });
+        copy_to_texture(&texture, bytes_per_pixel, &uploads, device, queue);
+
+        Ok(Texture {
+            texture,
+            texture_extent,
+            format,
+            mip_level_count,
+            array_layer_count,
+            view_dimension
+        })
+    }
+
+    /// Loads an image file as a texture, picking the decoder by its extension.
+    pub fn new(path: &Path, context: &mut Context) -> Result<Texture, TextureError> {
+        Self::new_with_options(path, &TextureOptions::default(), context)
+    }
+
+    pub fn new_with_options(path: &Path, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let img = open_image(path)?;
+        Self::new_from_image(img, options, context)
+    }
+
+    /// Decodes an image file already in memory as a texture, picking the decoder by its
+    /// contents.
+    pub fn new_from_memory(bytes: &[u8], context: &mut Context) -> Result<Texture, TextureError> {
+        Self::new_from_memory_with_options(bytes, &TextureOptions::default(), context)
+    }
+
+    pub fn new_from_memory_with_options(bytes: &[u8], options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let format = image::guess_format(bytes)?;
+        check_image_size(image::io::Reader::with_format(Cursor::new(bytes), format).into_dimensions()?)?;
+        let img = image::load_from_memory_with_format(bytes, format)?;
+        Self::new_from_image(img, options, context)
+    }
+
+    fn new_from_image(img: image::DynamicImage, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let (w, h) = img.dimensions();
+        let data = img.into_rgba8().into_raw();
+        Ok(Self::new_from_data(w, h, options.rgba_format(), &data, options, &context.device, &mut context.queue)?)
+    }
+
+    /// Creates a texture from tightly packed 8-bit RGBA pixels.
+    pub fn new_from_rgba(width: u32, height: u32, data: &[u8], context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_from_rgba_with_options(width, height, data, &TextureOptions::default(), context)
+    }
+
+    pub fn new_from_rgba_with_options(width: u32, height: u32, data: &[u8], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_from_data(width, height, options.rgba_format(), data, options, &context.device, &mut context.queue)
+    }
+
+    /// Creates a texture from tightly packed pixels of any format with a fixed size per
+    /// pixel, little endian. `options.srgb` is ignored, the format decides.
+    pub fn new_with_format(width: u32, height: u32, format: wgpu::TextureFormat, data: &[u8], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_from_data(width, height, format, data, options, &context.device, &mut context.queue)
+    }
+
+    /// Creates a texture from prebuilt mip levels, given as `layers[layer][level]`. Each level
+    /// is tightly packed and half the size of the one before, rounded down to at least 1. Cube
+    /// views take six layers per cube, in +X, -X, +Y, -Y, +Z, -Z order.
+    pub fn new_from_layers(width: u32, height: u32, format: wgpu::TextureFormat, layers: &[Vec<Vec<u8>>], view_dimension: wgpu::TextureViewDimension, context: &mut Context) -> Result<Texture, TextureError> {
+        check_image_size((width, height))?;
+        Ok(Self::new_from_levels(width, height, format, layers, view_dimension, &context.device, &mut context.queue)?)
+    }
+
+    /// Creates a cube map from six square faces of any format with a fixed size per pixel,
+    /// in +X, -X, +Y, -Y, +Z, -Z order. `options.srgb` is ignored, the format decides.
+    pub fn new_cube_with_format(size: u32, format: wgpu::TextureFormat, faces: &[&[u8]; 6], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        let bytes_per_pixel = match texture_format::bytes_per_pixel(format) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => return Err("Texture format can't be uploaded from memory!")
+        };
+        if faces.iter().any(|face| face.len() as u64 != size as u64 * size as u64 * bytes_per_pixel as u64) {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let layers: Vec<Vec<Vec<u8>>> = faces.iter()
+            .map(|face| mip_chain(size, size, format, face, options))
+            .collect();
+        Self::new_from_levels(size, size, format, &layers, wgpu::TextureViewDimension::Cube, &context.device, &mut context.queue)
+    }
+
+    /// Creates a cube map from six square faces of tightly packed 8-bit RGBA pixels.
+    pub fn new_cube_from_rgba(size: u32, faces: &[&[u8]; 6], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_cube_with_format(size, options.rgba_format(), faces, options, context)
+    }
+
+    /// Loads a cube map from six square images of the same size, in +X, -X, +Y, -Y, +Z, -Z
+    /// order.
+    pub fn new_cube(paths: &[&Path; 6], options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let mut faces = Vec::with_capacity(6);
+        for path in paths {
+            faces.push(open_image(path)?.into_rgba8());
+        }
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(TextureError::Invalid("Cube map faces need to be square and the same size!"));
+        }
+
+        let faces = [&*faces[0], &*faces[1], &*faces[2], &*faces[3], &*faces[4], &*faces[5]];
+        Ok(Self::new_cube_from_rgba(size, &faces, options, context)?)
+    }
+
+    /// Creates a cube map with `face_size` pixel wide faces from an equirectangular panorama,
+    /// whose middle faces -Z. Only formats mip levels can be generated for are supported.
+    /// A face size of about a quarter of the panorama's width keeps most of its detail.
+    pub fn new_cube_from_equirectangular(width: u32, height: u32, format: wgpu::TextureFormat, data: &[u8], face_size: u32, options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let (channels, encoding) = match texture_format::filterable_channels(format) {
+            Some(filterable) => filterable,
+            None => return Err(TextureError::Invalid("Panorama format can't be resampled!"))
+        };
+        if width == 0 || height == 0 || face_size == 0 {
+            return Err(TextureError::Invalid("Texture size can't be zero!"));
+        }
+        check_image_size((width, height))?;
+        check_image_size((face_size, face_size))?;
+        let bytes_per_pixel = texture_format::bytes_per_pixel(format).unwrap_or(0);
+        if data.len() as u64 != width as u64 * height as u64 * bytes_per_pixel as u64 {
+            return Err(TextureError::Invalid("Texture data length doesn't match its size and format!"));
+        }
+
+        let values = texture_format::decode(data, channels, encoding);
+        let faces: Vec<Vec<u8>> = equirectangular_to_cube(width, height, &values, channels, face_size)
+            .iter()
+            .map(|face| texture_format::encode(face, channels, encoding))
+            .collect();
+        let faces = [&*faces[0], &*faces[1], &*faces[2], &*faces[3], &*faces[4], &*faces[5]];
+        Ok(Self::new_cube_with_format(face_size, format, &faces, options, context)?)
+    }
+
+    /// Creates a texture array from same-size layers of any format with a fixed size per
+    /// pixel, such as terrain splatting layers. `options.srgb` is ignored, the format decides.
+    pub fn new_array_with_format(width: u32, height: u32, format: wgpu::TextureFormat, layers: &[&[u8]], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        let bytes_per_pixel = match texture_format::bytes_per_pixel(format) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => return Err("Texture format can't be uploaded from memory!")
+        };
+        if layers.iter().any(|layer| layer.len() as u64 != width as u64 * height as u64 * bytes_per_pixel as u64) {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let layers: Vec<Vec<Vec<u8>>> = layers.iter()
+            .map(|layer| mip_chain(width, height, format, layer, options))
+            .collect();
+        Self::new_from_levels(width, height, format, &layers, wgpu::TextureViewDimension::D2Array, &context.device, &mut context.queue)
+    }
+
+    /// Creates a texture array from same-size layers of tightly packed 8-bit RGBA pixels.
+    pub fn new_array_from_rgba(width: u32, height: u32, layers: &[&[u8]], options: &TextureOptions, context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_array_with_format(width, height, options.rgba_format(), layers, options, context)
+    }
+
+    /// Loads a texture array from images of the same size, one layer each.
+    pub fn new_array(paths: &[&Path], options: &TextureOptions, context: &mut Context) -> Result<Texture, TextureError> {
+        let mut layers = Vec::with_capacity(paths.len());
+        for path in paths {
+            layers.push(open_image(path)?.into_rgba8());
+        }
+        if layers.is_empty() {
+            return Err(TextureError::Invalid("Texture needs at least one layer!"));
+        }
+        let (width, height) = layers[0].dimensions();
+        if layers.iter().any(|layer| layer.dimensions() != (width, height)) {
+            return Err(TextureError::Invalid("Texture array layers need to be the same size!"));
+        }
+
+        let layers: Vec<&[u8]> = layers.iter().map(|layer| &**layer).collect();
+        Ok(Self::new_array_from_rgba(width, height, &layers, options, context)?)
+    }
+
+    /// Creates a 3D texture from `depth` slices of `width` x `height` tightly packed pixels,
+    /// for volumetric data such as fog densities or color grading lookup tables. Volumes
+    /// always have a single mip level.
+    pub fn new_3d(width: u32, height: u32, depth: u32, format: wgpu::TextureFormat, data: &[u8], context: &mut Context) -> Result<Texture, &'static str> {
+        Self::new_3d_from_data(width, height, depth, format, data, &context.device, &mut context.queue)
+    }
+
+    pub(super) fn new_3d_from_data(width: u32, height: u32, depth: u32, format: wgpu::TextureFormat, data: &[u8], device: &wgpu::Device, queue: &mut wgpu::Queue) -> Result<Texture, &'static str> {
+        let bytes_per_pixel = match texture_format::bytes_per_pixel(format) {
+            Some(bytes_per_pixel) => bytes_per_pixel,
+            None => return Err("Texture format can't be uploaded from memory!")
+        };
+        if width == 0 || height == 0 || depth == 0 {
+            return Err("Texture size can't be zero!");
+        }
+        if data.len() as u64 != width as u64 * height as u64 * depth as u64 * bytes_per_pixel as u64 {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let texture_extent = wgpu::Extent3d {
+            width,
+            height,
+            depth
+        };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             size: texture_extent,
             array_layer_count: 1,
             mip_level_count: 1,
             sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            dimension: wgpu::TextureDimension::D3,
+            format,
             usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST
         });
-        let temp_buf = device.create_buffer_mapped(data.len(), wgpu::BufferUsage::COPY_SRC)
-            .fill_from_slice(data.as_slice());
+        let upload = Upload {
+            mip_level: 0,
+            array_layer: 0,
+            origin: [0, 0, 0],
+            size: [width, height, depth],
+            data
+        };
+        copy_to_texture(&texture, bytes_per_pixel, &[upload], device, queue);
+
+        Ok(Texture {
+            texture,
+            texture_extent,
+            format,
+            mip_level_count: 1,
+            array_layer_count: 1,
+            view_dimension: wgpu::TextureViewDimension::D3
+        })
+    }
+
+    /// Creates an empty depth texture that can be rendered to and sampled, for example as a
+    /// shadow map with a comparison sampler.
+    pub fn new_depth(width: u32, height: u32, format: wgpu::TextureFormat, context: &Context) -> Result<Texture, &'static str> {
+        if !texture_format::is_depth(format) {
+            return Err("Depth textures need a depth format!");
+        }
+        if width == 0 || height == 0 {
+            return Err("Texture size can't be zero!");
+        }
 
-        let mut init_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            todo: 0
+        let texture_extent = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1
+        };
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            size: texture_extent,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT
         });
-        init_encoder.copy_buffer_to_texture(
-            wgpu::BufferCopyView {
-                buffer: &temp_buf,
-                offset: 0,
-                row_pitch: 4 * width,
-                image_height: height
-            },
-            wgpu::TextureCopyView {
-                texture: &texture,
-                mip_level: 0,
-                array_layer: 0,
-                origin: wgpu::Origin3d {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0
-                }
-            },
-            texture_extent
-        );
-        queue.submit(&[init_encoder.finish()]);
 
-        Texture {
+        Ok(Texture {
             texture,
-            texture_extent
+            texture_extent,
+            format,
+            mip_level_count: 1,
+            array_layer_count: 1,
+            view_dimension: wgpu::TextureViewDimension::D2
+        })
+    }
+
+    /// Replaces all pixels with tightly packed pixels of the texture's format, the layers or
+    /// depth slices back to back. Mip levels are regenerated from the new pixels. The texture
+    /// stays bound wherever it was, so this can stream video frames every frame.
+    pub fn write(&mut self, data: &[u8], context: &mut Context) -> Result<(), &'static str> {
+        let bytes_per_pixel = self.upload_bytes_per_pixel()?;
+        let layer_size = self.texture_extent.width as usize * self.texture_extent.height as usize * bytes_per_pixel as usize;
+        if data.len() as u64 != layer_size as u64 * self.layer_count() as u64 {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let mut layers = Vec::with_capacity(self.layer_count() as usize);
+        for layer_data in data.chunks(layer_size) {
+            layers.push(self.mip_levels(layer_data)?);
+        }
+        let uploads: Vec<Upload> = layers.iter()
+            .enumerate()
+            .flat_map(|(layer, levels)| self.layer_uploads(layer as u32, levels))
+            .collect();
+        copy_to_texture(&self.texture, bytes_per_pixel, &uploads, &context.device, &mut context.queue);
+        Ok(())
+    }
+
+    /// Replaces one layer of a texture array or cube map, or one depth slice of a 3D texture,
+    /// with tightly packed pixels of the texture's format. Mip levels are regenerated from the
+    /// new pixels.
+    pub fn update_layer(&mut self, layer: u32, data: &[u8], context: &mut Context) -> Result<(), &'static str> {
+        let bytes_per_pixel = self.upload_bytes_per_pixel()?;
+        if layer >= self.layer_count() {
+            return Err("Texture layer is out of range!");
         }
+        if data.len() as u64 != self.texture_extent.width as u64 * self.texture_extent.height as u64 * bytes_per_pixel as u64 {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+
+        let levels = self.mip_levels(data)?;
+        let uploads = self.layer_uploads(layer, &levels);
+        copy_to_texture(&self.texture, bytes_per_pixel, &uploads, &context.device, &mut context.queue);
+        Ok(())
     }
 
-    pub fn new<UT, V>(path: &Path, renderer: &mut Renderer<UT, V>) -> Texture {
-        let (w, h, data) = {
-            let img = image::open(&path).unwrap();
-            let w = img.width();
-            let h = img.height();
-            (w, h, img.into_rgba().clone().into_raw())
+    /// Replaces a `width` x `height` rectangle of the base mip level, at `x`, `y`, with tightly
+    /// packed pixels of the texture's format. Only the first layer or depth slice is written
+    /// and smaller mip levels keep their old pixels, so dynamic atlases and canvases updated
+    /// this way are best created without mipmaps.
+    pub fn write_region(&mut self, x: u32, y: u32, width: u32, height: u32, data: &[u8], context: &mut Context) -> Result<(), &'static str> {
+        let bytes_per_pixel = self.upload_bytes_per_pixel()?;
+        if x as u64 + width as u64 > self.texture_extent.width as u64 || y as u64 + height as u64 > self.texture_extent.height as u64 {
+            return Err("Texture region is out of bounds!");
+        }
+        if data.len() as u64 != width as u64 * height as u64 * bytes_per_pixel as u64 {
+            return Err("Texture data length doesn't match its size and format!");
+        }
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let upload = Upload {
+            mip_level: 0,
+            array_layer: 0,
+            origin: [x, y, 0],
+            size: [width, height, 1],
+            data
         };
-        Self::new_from_data(w, h, &data, &mut renderer.device, &mut renderer.queue)
+        copy_to_texture(&self.texture, bytes_per_pixel, &[upload], &context.device, &mut context.queue);
+        Ok(())
+    }
+
+    fn upload_bytes_per_pixel(&self) -> Result<u32, &'static str> {
+        match texture_format::bytes_per_pixel(self.format) {
+            Some(bytes_per_pixel) => Ok(bytes_per_pixel),
+            None => Err("Texture format can't be uploaded from memory!")
+        }
+    }
+
+    /// Array layers, or depth slices of a 3D texture.
+    fn layer_count(&self) -> u32 {
+        if self.view_dimension == wgpu::TextureViewDimension::D3 {
+            self.texture_extent.depth
+        } else {
+            self.array_layer_count
+        }
+    }
+
+    /// Builds as many mip levels from one layer's pixels as the texture has.
+    fn mip_levels(&self, data: &[u8]) -> Result<Vec<Vec<u8>>, &'static str> {
+        let options = TextureOptions {
+            generate_mipmaps: self.mip_level_count > 1,
+            ..TextureOptions::default()
+        };
+        let mut levels = mip_chain(self.texture_extent.width, self.texture_extent.height, self.format, data, &options);
+        if levels.len() < self.mip_level_count as usize {
+            return Err("Texture's mip levels can't be generated for its format!");
+        }
+        levels.truncate(self.mip_level_count as usize);
+        Ok(levels)
+    }
+
+    fn layer_uploads<'a>(&self, layer: u32, levels: &'a [Vec<u8>]) -> Vec<Upload<'a>> {
+        let is_3d = self.view_dimension == wgpu::TextureViewDimension::D3;
+        levels.iter()
+            .enumerate()
+            .map(|(level, level_data)| Upload {
+                mip_level: level as u32,
+                array_layer: if is_3d { 0 } else { layer },
+                origin: [0, 0, if is_3d { layer } else { 0 }],
+                size: [(self.texture_extent.width >> level).max(1), (self.texture_extent.height >> level).max(1), 1],
+                data: level_data
+            })
+            .collect()
+    }
+
+    pub fn get_format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn get_mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    pub fn get_size(&self) -> (u32, u32, u32) {
+        (self.texture_extent.width, self.texture_extent.height, self.texture_extent.depth)
+    }
+
+    pub fn get_array_layer_count(&self) -> u32 {
+        self.array_layer_count
+    }
+
+    pub fn get_view_dimension(&self) -> wgpu::TextureViewDimension {
+        self.view_dimension
     }
 
     pub fn get_view(&self) -> wgpu::TextureView {
-        self.texture.create_default_view()
+        self.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: self.format,
+            dimension: self.view_dimension,
+            aspect: wgpu::TextureAspect::All,
+            base_mip_level: 0,
+            level_count: self.mip_level_count,
+            base_array_layer: 0,
+            array_layer_count: self.array_layer_count
+        })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        assert!(check_image_size((MAX_IMAGE_SIZE, MAX_IMAGE_SIZE)).is_ok());
+        assert!(matches!(check_image_size((MAX_IMAGE_SIZE + 1, 1)), Err(TextureError::TooLarge(width, 1)) if width == MAX_IMAGE_SIZE + 1));
+    }
+
+    #[test]
+    fn image_errors_are_sorted_by_cause() {
+        let unknown = image::guess_format(b"not an image").unwrap_err();
+        assert!(matches!(TextureError::from(unknown), TextureError::UnsupportedFormat(_)));
+        let corrupt = image::load_from_memory_with_format(b"\x89PNG\r\n\x1a\n", image::ImageFormat::Png).unwrap_err();
+        assert!(matches!(TextureError::from(corrupt), TextureError::Decode(_)));
+        let missing = open_image(Path::new("/nonexistent/texture.png")).err().unwrap();
+        assert!(matches!(missing, TextureError::Io(_)));
+    }
+}
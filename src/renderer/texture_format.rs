@@ -0,0 +1,189 @@
+/// How the channels of an uploadable format are stored, for filtering them on the CPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChannelEncoding {
+    Unorm8,
+    /// 8-bit sRGB color channels, alpha is stored linearly.
+    Srgb8,
+    Float16,
+    Float32
+}
+
+/// The size of one pixel of `format` in bytes, or `None` for formats that can't be
+/// uploaded from memory.
+pub fn bytes_per_pixel(format: wgpu::TextureFormat) -> Option<u32> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        R8Unorm | R8Snorm | R8Uint | R8Sint => 1,
+        R16Unorm | R16Snorm | R16Uint | R16Sint | R16Float
+            | Rg8Unorm | Rg8Snorm | Rg8Uint | Rg8Sint => 2,
+        R32Uint | R32Sint | R32Float
+            | Rg16Unorm | Rg16Snorm | Rg16Uint | Rg16Sint | Rg16Float
+            | Rgba8Unorm | Rgba8UnormSrgb | Rgba8Snorm | Rgba8Uint | Rgba8Sint
+            | Bgra8Unorm | Bgra8UnormSrgb
+            | Rgb10a2Unorm | Rg11b10Float => 4,
+        Rg32Uint | Rg32Sint | Rg32Float
+            | Rgba16Unorm | Rgba16Snorm | Rgba16Uint | Rgba16Sint | Rgba16Float => 8,
+        Rgba32Uint | Rgba32Sint | Rgba32Float => 16,
+        Depth32Float | Depth24Plus | Depth24PlusStencil8 => return None
+    })
+}
+
+pub fn is_depth(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Depth32Float | wgpu::TextureFormat::Depth24Plus | wgpu::TextureFormat::Depth24PlusStencil8
+    )
+}
+
+/// The channel count and encoding of formats mip levels can be generated for.
+pub(crate) fn filterable_channels(format: wgpu::TextureFormat) -> Option<(usize, ChannelEncoding)> {
+    use wgpu::TextureFormat::*;
+    Some(match format {
+        R8Unorm => (1, ChannelEncoding::Unorm8),
+        Rg8Unorm => (2, ChannelEncoding::Unorm8),
+        Rgba8Unorm | Bgra8Unorm => (4, ChannelEncoding::Unorm8),
+        Rgba8UnormSrgb | Bgra8UnormSrgb => (4, ChannelEncoding::Srgb8),
+        R16Float => (1, ChannelEncoding::Float16),
+        Rg16Float => (2, ChannelEncoding::Float16),
+        Rgba16Float => (4, ChannelEncoding::Float16),
+        R32Float => (1, ChannelEncoding::Float32),
+        Rg32Float => (2, ChannelEncoding::Float32),
+        Rgba32Float => (4, ChannelEncoding::Float32),
+        _ => return None
+    })
+}
+
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 * 2.0f32.powi(-24);
+            if sign != 0 { -magnitude } else { magnitude }
+        },
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13))
+    }
+}
+
+/// Rounds to the nearest half float, values out of range become infinity.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let rounding = (mantissa >> (shift - 1)) & 1;
+        return sign | ((mantissa >> shift) + rounding) as u16;
+    }
+    // A carry out of the mantissa correctly bumps the exponent.
+    (sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16) + ((mantissa >> 12) & 1) as u16
+}
+
+/// Decodes pixels of a filterable format to one float per channel, color channels of sRGB
+/// formats converted to linear.
+pub(crate) fn decode(data: &[u8], channels: usize, encoding: ChannelEncoding) -> Vec<f32> {
+    match encoding {
+        ChannelEncoding::Unorm8 => data.iter().map(|&value| value as f32 / 255.0).collect(),
+        ChannelEncoding::Srgb8 => {
+            let to_linear: Vec<f32> = (0..256).map(|value| srgb_to_linear(value as f32 / 255.0)).collect();
+            data.iter()
+                .enumerate()
+                .map(|(i, &value)| if i % channels == 3 { value as f32 / 255.0 } else { to_linear[value as usize] })
+                .collect()
+        },
+        ChannelEncoding::Float16 => data.chunks_exact(2)
+            .map(|bytes| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .collect(),
+        ChannelEncoding::Float32 => data.chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+}
+
+/// The inverse of `decode`.
+pub(crate) fn encode(values: &[f32], channels: usize, encoding: ChannelEncoding) -> Vec<u8> {
+    let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match encoding {
+        ChannelEncoding::Unorm8 => values.iter().map(|&value| to_byte(value)).collect(),
+        ChannelEncoding::Srgb8 => values.iter()
+            .enumerate()
+            .map(|(i, &value)| if i % channels == 3 { to_byte(value) } else { to_byte(linear_to_srgb(value)) })
+            .collect(),
+        ChannelEncoding::Float16 => values.iter().flat_map(|&value| f32_to_f16(value).to_le_bytes().to_vec()).collect(),
+        ChannelEncoding::Float32 => values.iter().flat_map(|&value| value.to_le_bytes().to_vec()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_half_floats_round_trip() {
+        for &value in &[0.0, 1.0, -2.5, 0.5, 1024.0, 65504.0, 6.103_515_6e-5] {
+            assert_eq!(f16_to_f32(f32_to_f16(value)), value);
+        }
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        for &value in &[0.1f32, 0.333, 2.2, -123.456, 40000.0] {
+            let error = (f16_to_f32(f32_to_f16(value)) - value).abs();
+            assert!(error <= value.abs() * 2.0f32.powi(-11), "{} lost {}", value, error);
+        }
+    }
+
+    #[test]
+    fn subnormal_half_floats_round_trip() {
+        let smallest = 2.0f32.powi(-24);
+        assert_eq!(f32_to_f16(smallest), 0x0001);
+        assert_eq!(f16_to_f32(0x0001), smallest);
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * smallest);
+        for &value in &[3.0 * smallest, 1000.0 * smallest, -17.0 * smallest] {
+            assert_eq!(f16_to_f32(f32_to_f16(value)), value);
+        }
+        let error = (f16_to_f32(f32_to_f16(1e-5)) - 1e-5).abs();
+        assert!(error <= smallest / 2.0);
+        assert_eq!(f32_to_f16(1e-10), 0);
+        assert_eq!(f32_to_f16(-1e-10), 0x8000);
+    }
+
+    #[test]
+    fn out_of_range_half_floats_become_infinity() {
+        assert_eq!(f32_to_f16(70000.0), 0x7c00);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(-1e10), 0xfc00);
+        assert_eq!(f16_to_f32(f32_to_f16(f32::INFINITY)), f32::INFINITY);
+        assert_eq!(f16_to_f32(f32_to_f16(f32::NEG_INFINITY)), f32::NEG_INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+}
@@ -0,0 +1,29 @@
+pub(super) struct WindowSurface {
+    pub(super) swap_chain: wgpu::SwapChain,
+    pub(super) sc_desc: wgpu::SwapChainDescriptor,
+    pub(super) surface: wgpu::Surface
+}
+
+impl WindowSurface {
+    pub(super) fn new(window: &winit::window::Window, device: &wgpu::Device, format: wgpu::TextureFormat, present_mode: wgpu::PresentMode) -> WindowSurface {
+        let size = window.inner_size();
+        let surface = wgpu::Surface::create(window);
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        WindowSurface {
+            swap_chain,
+            sc_desc,
+            surface
+        }
+    }
+
+    pub(super) fn recreate_swap_chain(&mut self, device: &wgpu::Device) {
+        self.swap_chain = device.create_swap_chain(&self.surface, &self.sc_desc);
+    }
+}